@@ -0,0 +1,283 @@
+use crate::platform::Screenshot;
+use raylib::{ffi, ffi::MeasureText, prelude::*};
+use std::cell::RefCell;
+use std::ffi::CString;
+use std::rc::Rc;
+
+// the drawing primitives games and effects use, so they can draw without a window
+pub trait Canvas {
+    fn clear_background(&mut self, color: Color);
+    fn draw_rectangle(&mut self, x: i32, y: i32, width: i32, height: i32, color: Color);
+    fn draw_circle(&mut self, x: i32, y: i32, radius: f32, color: Color);
+    fn draw_text(&mut self, text: &str, x: i32, y: i32, font_size: i32, color: Color);
+    // width of the text in pixels when drawn with draw_text
+    fn measure_text(&self, text: &str, font_size: i32) -> i32;
+    // the picture at its own size with the top left corner at x, y, turned by rotation
+    // degrees around its center and multiplied by tint
+    fn draw_image(&mut self, image: &Rc<Screenshot>, x: f32, y: f32, rotation: f32, tint: Color);
+}
+
+thread_local! {
+    // the last screenshot drawn and its texture, uploading one every frame would be far too slow
+    static TEXTURE: RefCell<Option<(u64, ffi::Texture2D)>> = const { RefCell::new(None) };
+}
+
+impl Canvas for RaylibDrawHandle<'_> {
+    fn clear_background(&mut self, color: Color) {
+        RaylibDraw::clear_background(self, color);
+    }
+
+    fn draw_rectangle(&mut self, x: i32, y: i32, width: i32, height: i32, color: Color) {
+        RaylibDraw::draw_rectangle(self, x, y, width, height, color);
+    }
+
+    fn draw_circle(&mut self, x: i32, y: i32, radius: f32, color: Color) {
+        RaylibDraw::draw_circle(self, x, y, radius, color);
+    }
+
+    fn draw_text(&mut self, text: &str, x: i32, y: i32, font_size: i32, color: Color) {
+        RaylibDraw::draw_text(self, text, x, y, font_size, color);
+    }
+
+    fn measure_text(&self, text: &str, font_size: i32) -> i32 {
+        let text = CString::new(text).unwrap_or_default();
+        unsafe { MeasureText(text.as_ptr(), font_size) }
+    }
+
+    fn draw_image(&mut self, image: &Rc<Screenshot>, x: f32, y: f32, rotation: f32, tint: Color) {
+        TEXTURE.with(|cache| {
+            let mut cache = cache.borrow_mut();
+            if cache.as_ref().is_none_or(|(id, _)| *id != image.id()) {
+                if let Some((_, old)) = cache.take() {
+                    unsafe { ffi::UnloadTexture(old) };
+                }
+                // raylib copies the pixels to the gpu and does not keep the pointer
+                let raw = ffi::Image {
+                    data: image.pixels.as_ptr() as *mut std::ffi::c_void,
+                    width: image.width,
+                    height: image.height,
+                    mipmaps: 1,
+                    format: ffi::PixelFormat::PIXELFORMAT_UNCOMPRESSED_R8G8B8A8 as i32,
+                };
+                *cache = Some((image.id(), unsafe { ffi::LoadTextureFromImage(raw) }));
+            }
+            let Some((_, texture)) = cache.as_ref() else {
+                return;
+            };
+            let (width, height) = (image.width as f32, image.height as f32);
+            unsafe {
+                ffi::DrawTexturePro(
+                    *texture,
+                    ffi::Rectangle {
+                        x: 0.0,
+                        y: 0.0,
+                        width,
+                        height,
+                    },
+                    ffi::Rectangle {
+                        x: x + width / 2.0,
+                        y: y + height / 2.0,
+                        width,
+                        height,
+                    },
+                    ffi::Vector2 {
+                        x: width / 2.0,
+                        y: height / 2.0,
+                    },
+                    rotation,
+                    tint.into(),
+                );
+            }
+        });
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum DrawCommand {
+    Clear {
+        color: [u8; 4],
+    },
+    Rectangle {
+        x: i32,
+        y: i32,
+        width: i32,
+        height: i32,
+        color: [u8; 4],
+    },
+    Circle {
+        x: i32,
+        y: i32,
+        radius: f32,
+        color: [u8; 4],
+    },
+    Text {
+        text: String,
+        x: i32,
+        y: i32,
+        font_size: i32,
+        color: [u8; 4],
+    },
+    Image {
+        image: Rc<Screenshot>,
+        x: f32,
+        y: f32,
+        rotation: f32,
+        color: [u8; 4],
+    },
+}
+
+fn rgba(color: Color) -> [u8; 4] {
+    [color.r, color.g, color.b, color.a]
+}
+
+// canvas that only writes down what was drawn
+#[derive(Default)]
+pub struct Recorder {
+    pub commands: Vec<DrawCommand>,
+}
+
+impl Recorder {
+    pub fn new() -> Recorder {
+        Recorder { commands: vec![] }
+    }
+
+    // the commands of the last frame, leaving the recorder empty for the next one
+    pub fn take(&mut self) -> Vec<DrawCommand> {
+        std::mem::take(&mut self.commands)
+    }
+
+    // one line per command, stable enough to compare against a saved snapshot
+    #[cfg(test)]
+    pub fn snapshot(&self) -> String {
+        let mut out = String::new();
+        for command in &self.commands {
+            out.push_str(&format!("{command:?}\n"));
+        }
+        out
+    }
+}
+
+impl Canvas for Recorder {
+    fn clear_background(&mut self, color: Color) {
+        self.commands.push(DrawCommand::Clear { color: rgba(color) });
+    }
+
+    fn draw_rectangle(&mut self, x: i32, y: i32, width: i32, height: i32, color: Color) {
+        self.commands.push(DrawCommand::Rectangle {
+            x,
+            y,
+            width,
+            height,
+            color: rgba(color),
+        });
+    }
+
+    fn draw_circle(&mut self, x: i32, y: i32, radius: f32, color: Color) {
+        self.commands.push(DrawCommand::Circle {
+            x,
+            y,
+            radius,
+            color: rgba(color),
+        });
+    }
+
+    fn draw_text(&mut self, text: &str, x: i32, y: i32, font_size: i32, color: Color) {
+        self.commands.push(DrawCommand::Text {
+            text: text.to_string(),
+            x,
+            y,
+            font_size,
+            color: rgba(color),
+        });
+    }
+
+    fn measure_text(&self, text: &str, font_size: i32) -> i32 {
+        // close to raylib's default font: half the size per glyph plus spacing
+        let spacing = (font_size / 10).max(1);
+        let count = text.chars().count() as i32;
+        count * (font_size / 2 + spacing) - spacing.min(count * spacing)
+    }
+
+    fn draw_image(&mut self, image: &Rc<Screenshot>, x: f32, y: f32, rotation: f32, tint: Color) {
+        self.commands.push(DrawCommand::Image {
+            image: image.clone(),
+            x,
+            y,
+            rotation,
+            color: rgba(tint),
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Config;
+    use crate::game_handler::{Game, GameContext};
+    use crate::games::{avoider::Avoider, circles::Circles, pong::Pong};
+    use rand::{SeedableRng, rngs::StdRng};
+
+    // starts game at level 0 in area and records its first frame
+    fn first_frame(game: &mut dyn Game, area: Rectangle, seed: u64) -> Recorder {
+        let mut rng = StdRng::seed_from_u64(seed);
+        game.set_difficulty(0.0);
+        game.on_start(&mut GameContext {
+            area,
+            rng: &mut rng,
+        });
+        let mut recorder = Recorder::new();
+        game.draw(&mut recorder, 1.0);
+        recorder
+    }
+
+    #[test]
+    fn pong_first_frame() {
+        // an area this size leaves pong no room to pick a random field size
+        let config = Config::default();
+        let recorder = first_frame(&mut Pong::new(&config.pong), rrect(0, 0, 1000, 500), 1);
+        assert_eq!(
+            recorder.snapshot(),
+            "Rectangle { x: 100, y: 50, width: 800, height: 400, color: [169, 169, 169, 255] }\n\
+             Circle { x: 500, y: 250, radius: 12.0, color: [0, 0, 0, 255] }\n\
+             Rectangle { x: 115, y: 200, width: 20, height: 100, color: [0, 0, 0, 255] }\n\
+             Rectangle { x: 865, y: 200, width: 20, height: 100, color: [0, 0, 0, 255] }\n"
+        );
+    }
+
+    #[test]
+    fn avoider_first_frame() {
+        let config = Config::default();
+        let recorder = first_frame(&mut Avoider::new(&config.avoider), rrect(0, 0, 1000, 500), 1);
+        assert_eq!(
+            recorder.snapshot(),
+            "Circle { x: 500, y: 250, radius: 20.0, color: [0, 0, 0, 255] }\n\
+             Circle { x: 500, y: 250, radius: 16.6, color: [173, 255, 47, 255] }\n\
+             Text { text: \"15\", x: 475, y: 11, font_size: 50, color: [0, 0, 0, 255] }\n\
+             Text { text: \"15\", x: 471, y: 9, font_size: 50, color: [0, 0, 0, 255] }\n\
+             Text { text: \"15\", x: 473, y: 10, font_size: 50, color: [255, 0, 0, 255] }\n"
+        );
+    }
+
+    #[test]
+    fn circles_first_frame() {
+        let config = Config::default();
+        let area = rrect(0, 0, 1000, 500);
+        let recorder = first_frame(&mut Circles::new(&config.circles), area, 1);
+        let circles: Vec<_> = recorder
+            .commands
+            .iter()
+            .filter_map(|command| match command {
+                DrawCommand::Circle { x, y, radius, .. } => Some((*x, *y, *radius)),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(circles.len(), config.circles.min_amount as usize);
+        for (x, y, radius) in circles {
+            assert_eq!(radius, 50.0);
+            assert!(area.check_collision_point_rec(Vector2::new(x as f32, y as f32)));
+        }
+        // the same seed lays out the same circles
+        let again = first_frame(&mut Circles::new(&config.circles), area, 1);
+        assert_eq!(recorder.snapshot(), again.snapshot());
+    }
+}
@@ -0,0 +1,1434 @@
+use crate::cooldown::Distribution;
+use crate::effects::Easing;
+use crate::overlay::MonitorMode;
+use crate::schedule::{self, Schedule, Window};
+use crate::selection::SelectionMode;
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+use yaml_rust2::{Yaml, YamlLoader};
+
+pub const DEFAULT_SETTINGS: &str = r##"Settings:
+  cooldown: 120.0       # time between games in seconds
+  # min_cooldown: 60.0    # set both to pick a random time between games instead
+  # max_cooldown: 300.0
+  cooldown_distribution: uniform # uniform or exponential (mostly short waits, sometimes long ones)
+  monitor: primary      # primary, all, random or a monitor index
+  selection: random     # random, no_repeat, shuffle or playlist
+  playlist: []          # game order for the playlist selection, e.g. [Pong, Circles]
+  # seed: 1234            # replays the same games, layouts and effects, --seed overrides it
+  tick_rate: 120.0      # game logic steps per second, independent of the frame rate
+  max_catch_up_steps: 8 # steps run at most per frame after a hitch, the rest is skipped
+  adaptive_difficulty: true # games get harder as you win and easier as you lose, kept between runs
+  safe_mode: false      # swap flashing and moving loss effects for a gentle dimming
+
+Pong:
+  enabled: true
+  weight: 1.0           # relative chance of being picked
+  ball_speed: 300.0     # pixels per second
+  paddle_speed: 200.0
+  ai_paddle_speed: 200.0
+
+Circles:
+  enabled: true
+  weight: 1.0
+  min_amount: 4         # circles in the first round
+  max_amount: 10
+  time_limit: 5.0       # seconds
+
+Avoider:
+  enabled: true
+  weight: 1.0
+  time_limit: 15.0      # seconds
+  spawn_timer: 0.6      # seconds between enemy spawns
+
+# what happens after a lost round, one effect is picked by weight
+Effects:
+  Flash:
+    enabled: true
+    weight: 1.0
+    duration: 5.0       # seconds after the worst loss, a narrow loss gets 40% of it
+    color: [112, 31, 22] # red, green, blue from 0 to 255, or a hex string like "#701f16"
+    alpha: 1.0          # opacity after the worst loss, from 0.0 to 1.0
+    fade_in: 0.0        # seconds
+    fade_out: 0.0
+    easing: linear      # linear, ease_in, ease_out or ease_in_out
+  Shake:                # shakes a screenshot of the desktop
+    enabled: true
+    weight: 1.0
+    duration: 3.0
+    color: [0, 0, 0]    # shows at the edges the moving picture uncovers
+    alpha: 1.0          # opacity of the picture
+    fade_in: 0.0
+    fade_out: 3.0       # how the shaking calms down
+    easing: ease_in
+  Melt:                 # the desktop runs down the screen, color shows where it is gone
+    enabled: true
+    weight: 1.0
+    duration: 5.0
+    color: [0, 0, 0]
+    alpha: 1.0
+    fade_in: 2.0        # for these fake desktop effects the fades are how far the change goes
+    fade_out: 1.0
+    easing: ease_in_out
+  Pixelate:             # the desktop turns into ever bigger blocks
+    enabled: true
+    weight: 1.0
+    duration: 5.0
+    color: [0, 0, 0]
+    alpha: 1.0
+    fade_in: 2.0
+    fade_out: 1.0
+    easing: ease_in_out
+  Flip:                 # the desktop is upside down
+    enabled: true
+    weight: 1.0
+    duration: 5.0
+    color: [0, 0, 0]
+    alpha: 1.0
+    fade_in: 0.0
+    fade_out: 0.0
+    easing: linear
+  Negative:             # the desktop colors are inverted
+    enabled: true
+    weight: 1.0
+    duration: 5.0
+    color: [0, 0, 0]
+    alpha: 1.0
+    fade_in: 1.0
+    fade_out: 1.0
+    easing: ease_in_out
+  Jigsaw:               # the desktop is cut up and the pieces swap places
+    enabled: true
+    weight: 1.0
+    duration: 5.0
+    color: [0, 0, 0]
+    alpha: 1.0
+    fade_in: 1.5
+    fade_out: 1.5
+    easing: ease_in_out
+  Invert:               # the cursor moves against the mouse, color is the text
+    enabled: false
+    weight: 1.0
+    duration: 5.0
+    color: [255, 255, 255]
+    alpha: 1.0
+    fade_in: 0.0
+    fade_out: 0.0
+    easing: linear
+  Drift:                # the cursor slides away on its own
+    enabled: false
+    weight: 1.0
+    duration: 5.0
+    color: [255, 255, 255]
+    alpha: 1.0
+    fade_in: 0.5
+    fade_out: 0.5
+    easing: linear
+  Lag:                  # the cursor trails behind the mouse
+    enabled: false
+    weight: 1.0
+    duration: 5.0
+    color: [255, 255, 255]
+    alpha: 1.0
+    fade_in: 0.5
+    fade_out: 0.5
+    easing: linear
+  Dim:                  # also stands in for the other effects in safe mode
+    enabled: false
+    weight: 1.0
+    duration: 5.0
+    color: [0, 0, 0]
+    alpha: 0.55
+    fade_in: 1.5
+    fade_out: 1.5
+    easing: ease_in_out
+
+# keyboard and mouse activity, 0 turns a limit off
+Activity:
+  start_idle_limit: 60.0  # games only start if there was input in this many seconds
+  pause_idle_limit: 10.0  # a running game pauses after this many seconds without input
+
+# when games may start, in local time. without allow windows games may start any time
+Schedule:
+  allow: []
+  # - days: weekdays      # mon..sun, weekdays, weekends, all or a list like [mon, wed]
+  #   from: "10:00"
+  #   to: "12:00"
+  deny: []
+  # - days: all
+  #   from: "22:00"       # windows may run past midnight
+  #   to: "08:00"
+"##;
+
+#[derive(Debug)]
+pub struct ConfigError {
+    // dotted path like Pong.ball_speed
+    pub key: String,
+    // 1-based line in settings.yaml when it could be found
+    pub line: Option<usize>,
+    pub message: String,
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.line {
+            Some(line) => write!(f, "{} (line {}): {}", self.key, line, self.message),
+            None => write!(f, "{}: {}", self.key, self.message),
+        }
+    }
+}
+
+impl std::error::Error for ConfigError {}
+
+#[derive(Clone, Debug)]
+pub struct SettingsConfig {
+    pub min_cooldown: f32,
+    pub max_cooldown: f32,
+    pub cooldown_distribution: Distribution,
+    pub monitor: MonitorMode,
+    pub selection: SelectionMode,
+    pub playlist: Vec<String>,
+    // fixed random seed, a new one every run when None
+    pub seed: Option<u64>,
+    // logic steps per second
+    pub tick_rate: f32,
+    pub max_catch_up_steps: i32,
+    pub adaptive_difficulty: bool,
+    pub safe_mode: bool,
+}
+
+#[derive(Clone, Debug)]
+pub struct PongConfig {
+    pub enabled: bool,
+    pub weight: f32,
+    pub ball_speed: f32,
+    pub paddle_speed: f32,
+    pub ai_paddle_speed: f32,
+}
+
+#[derive(Clone, Debug)]
+pub struct CirclesConfig {
+    pub enabled: bool,
+    pub weight: f32,
+    pub min_amount: i32,
+    pub max_amount: i32,
+    pub time_limit: f32,
+}
+
+#[derive(Clone, Debug)]
+pub struct AvoiderConfig {
+    pub enabled: bool,
+    pub weight: f32,
+    pub time_limit: f32,
+    pub spawn_timer: f32,
+}
+
+// one loss effect
+#[derive(Clone, Debug)]
+pub struct EffectConfig {
+    pub enabled: bool,
+    pub weight: f32,
+    // seconds after the worst loss
+    pub duration: f32,
+    pub color: [u8; 3],
+    // opacity after the worst loss, 0.0 to 1.0
+    pub alpha: f32,
+    // seconds to reach full strength and to disappear again
+    pub fade_in: f32,
+    pub fade_out: f32,
+    pub easing: Easing,
+}
+
+#[derive(Clone, Debug)]
+pub struct EffectsConfig {
+    pub flash: EffectConfig,
+    pub shake: EffectConfig,
+    pub melt: EffectConfig,
+    pub pixelate: EffectConfig,
+    pub flip: EffectConfig,
+    pub negative: EffectConfig,
+    pub jigsaw: EffectConfig,
+    pub invert: EffectConfig,
+    pub drift: EffectConfig,
+    pub lag: EffectConfig,
+    pub dim: EffectConfig,
+}
+
+#[derive(Clone, Debug)]
+pub struct ActivityConfig {
+    pub start_idle_limit: f32,
+    pub pause_idle_limit: f32,
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct Config {
+    pub settings: SettingsConfig,
+    pub pong: PongConfig,
+    pub circles: CirclesConfig,
+    pub avoider: AvoiderConfig,
+    pub effects: EffectsConfig,
+    pub activity: ActivityConfig,
+    pub schedule: Schedule,
+}
+
+impl Default for SettingsConfig {
+    fn default() -> SettingsConfig {
+        SettingsConfig {
+            min_cooldown: 120.0,
+            max_cooldown: 120.0,
+            cooldown_distribution: Distribution::Uniform,
+            monitor: MonitorMode::Primary,
+            selection: SelectionMode::Random,
+            playlist: vec![],
+            seed: None,
+            tick_rate: 120.0,
+            max_catch_up_steps: 8,
+            adaptive_difficulty: true,
+            safe_mode: false,
+        }
+    }
+}
+
+impl Default for PongConfig {
+    fn default() -> PongConfig {
+        PongConfig {
+            enabled: true,
+            weight: 1.0,
+            ball_speed: 300.0,
+            paddle_speed: 200.0,
+            ai_paddle_speed: 200.0,
+        }
+    }
+}
+
+impl Default for CirclesConfig {
+    fn default() -> CirclesConfig {
+        CirclesConfig {
+            enabled: true,
+            weight: 1.0,
+            min_amount: 4,
+            max_amount: 10,
+            time_limit: 5.0,
+        }
+    }
+}
+
+impl Default for AvoiderConfig {
+    fn default() -> AvoiderConfig {
+        AvoiderConfig {
+            enabled: true,
+            weight: 1.0,
+            time_limit: 15.0,
+            spawn_timer: 0.6,
+        }
+    }
+}
+
+// the cursor effects only differ in how they fade
+fn cursor_effect(fade: f32) -> EffectConfig {
+    EffectConfig {
+        enabled: false,
+        weight: 1.0,
+        duration: 5.0,
+        color: [255, 255, 255],
+        alpha: 1.0,
+        fade_in: fade,
+        fade_out: fade,
+        easing: Easing::Linear,
+    }
+}
+
+// the fake desktop effects only differ in how they fade
+fn desktop_effect(fade_in: f32, fade_out: f32, easing: Easing) -> EffectConfig {
+    EffectConfig {
+        enabled: true,
+        weight: 1.0,
+        duration: 5.0,
+        color: [0, 0, 0],
+        alpha: 1.0,
+        fade_in,
+        fade_out,
+        easing,
+    }
+}
+
+impl Default for EffectsConfig {
+    fn default() -> EffectsConfig {
+        EffectsConfig {
+            flash: EffectConfig {
+                enabled: true,
+                weight: 1.0,
+                duration: 5.0,
+                color: [112, 31, 22],
+                alpha: 1.0,
+                fade_in: 0.0,
+                fade_out: 0.0,
+                easing: Easing::Linear,
+            },
+            shake: EffectConfig {
+                enabled: true,
+                weight: 1.0,
+                duration: 3.0,
+                color: [0, 0, 0],
+                alpha: 1.0,
+                fade_in: 0.0,
+                fade_out: 3.0,
+                easing: Easing::EaseIn,
+            },
+            melt: desktop_effect(2.0, 1.0, Easing::EaseInOut),
+            pixelate: desktop_effect(2.0, 1.0, Easing::EaseInOut),
+            flip: desktop_effect(0.0, 0.0, Easing::Linear),
+            negative: desktop_effect(1.0, 1.0, Easing::EaseInOut),
+            jigsaw: desktop_effect(1.5, 1.5, Easing::EaseInOut),
+            invert: cursor_effect(0.0),
+            drift: cursor_effect(0.5),
+            lag: cursor_effect(0.5),
+            dim: EffectConfig {
+                enabled: false,
+                weight: 1.0,
+                duration: 5.0,
+                color: [0, 0, 0],
+                alpha: 0.55,
+                fade_in: 1.5,
+                fade_out: 1.5,
+                easing: Easing::EaseInOut,
+            },
+        }
+    }
+}
+
+impl Default for ActivityConfig {
+    fn default() -> ActivityConfig {
+        ActivityConfig {
+            start_idle_limit: 60.0,
+            pause_idle_limit: 10.0,
+        }
+    }
+}
+
+// one section, either a mapping or the old positional list
+struct Section<'a> {
+    // dotted path like Effects.Flash
+    name: String,
+    node: &'a Yaml,
+    // key for each position of the list format
+    order: &'static [&'static str],
+    source: &'a str,
+    // 1-based line of the key that opens the section and how far that key is indented
+    line: Option<usize>,
+    indent: usize,
+}
+
+fn indent(line: &str) -> usize {
+    line.len() - line.trim_start().len()
+}
+
+fn describe(value: &Yaml) -> String {
+    match value {
+        Yaml::Real(v) => v.clone(),
+        Yaml::Integer(v) => v.to_string(),
+        Yaml::String(v) => format!("\"{v}\""),
+        Yaml::Boolean(v) => v.to_string(),
+        Yaml::Array(_) => "a list".to_string(),
+        Yaml::Hash(_) => "a mapping".to_string(),
+        _ => "nothing".to_string(),
+    }
+}
+
+// line of a top-level key, 1-based
+fn section_line(source: &str, section: &str) -> Option<usize> {
+    source
+        .lines()
+        .position(|line| line.starts_with(section) && line[section.len()..].trim_start().starts_with(':'))
+        .map(|index| index + 1)
+}
+
+// one schedule entry like {days: weekdays, from: "10:00", to: "12:00"}
+fn window_from_yaml(item: &Yaml) -> Result<Window, String> {
+    if item.as_hash().is_none() {
+        return Err(format!("expected days, from and to, found {}", describe(item)));
+    }
+    let days = match &item["days"] {
+        Yaml::BadValue | Yaml::Null => [true; 7],
+        Yaml::String(name) => schedule::parse_days(name).ok_or_else(|| format!("unknown day \"{name}\""))?,
+        Yaml::Array(names) => {
+            let mut days = [false; 7];
+            for name in names {
+                let parsed = name
+                    .as_str()
+                    .and_then(schedule::parse_days)
+                    .ok_or_else(|| format!("unknown day {}", describe(name)))?;
+                for (day, on) in days.iter_mut().zip(parsed) {
+                    *day |= on;
+                }
+            }
+            days
+        }
+        other => return Err(format!("expected a day or a list of days, found {}", describe(other))),
+    };
+    let time = |key: &str| {
+        let value = &item[key];
+        value
+            .as_str()
+            .and_then(schedule::parse_time)
+            .ok_or_else(|| format!("{key} must be a time like \"10:00\", found {}", describe(value)))
+    };
+    Ok(Window::new(days, time("from")?, time("to")?))
+}
+
+// stands in for a section that could not be used
+static BAD_SECTION: Yaml = Yaml::BadValue;
+
+impl<'a> Section<'a> {
+    fn new(
+        root: &'a Yaml,
+        name: &'static str,
+        order: &'static [&'static str],
+        source: &'a str,
+        report: &mut Report,
+    ) -> Section<'a> {
+        Section::open(name.to_string(), &root[name], order, source, section_line(source, name), 0, report)
+    }
+
+    fn open(
+        name: String,
+        node: &'a Yaml,
+        order: &'static [&'static str],
+        source: &'a str,
+        line: Option<usize>,
+        indent: usize,
+        report: &mut Report,
+    ) -> Section<'a> {
+        let mut section = Section {
+            name,
+            node,
+            order,
+            source,
+            line,
+            indent,
+        };
+        match section.node {
+            Yaml::Hash(_) | Yaml::Array(_) | Yaml::BadValue | Yaml::Null => {}
+            other => {
+                report.errors.push(ConfigError {
+                    key: section.name.clone(),
+                    line,
+                    message: format!("expected a mapping of settings, found {}", describe(other)),
+                });
+                section.node = &BAD_SECTION;
+            }
+        }
+        section.check_keys(report);
+        section
+    }
+
+    // a mapping inside this one, like Flash inside Effects
+    fn child(&self, key: &'static str, order: &'static [&'static str], report: &mut Report) -> Section<'a> {
+        let line = self.key_line(key);
+        let indent = line
+            .and_then(|number| self.source.lines().nth(number - 1))
+            .map(indent)
+            .unwrap_or(self.indent);
+        let node = match self.get(key) {
+            Some(node) => node,
+            None => &BAD_SECTION,
+        };
+        Section::open(format!("{}.{}", self.name, key), node, order, self.source, line, indent, report)
+    }
+
+    // lines belonging to this section, paired with their 1-based line number
+    fn lines(&self) -> Vec<(usize, &'a str)> {
+        let Some(start) = self.line else {
+            return vec![];
+        };
+        self.source
+            .lines()
+            .enumerate()
+            .skip(start)
+            .take_while(|(_, line)| {
+                line.trim().is_empty() || line.trim_start().starts_with('#') || indent(line) > self.indent
+            })
+            .map(|(index, line)| (index + 1, line.trim()))
+            .collect()
+    }
+
+    // line of a key in this section, None when it is not written out
+    fn key_line(&self, key: &str) -> Option<usize> {
+        let lines = self.lines();
+        let found = match self.node {
+            Yaml::Array(_) => {
+                let position = self.order.iter().position(|k| *k == key)?;
+                lines.iter().filter(|(_, line)| line.starts_with('-')).nth(position)
+            }
+            _ => lines
+                .iter()
+                .find(|(_, line)| line.starts_with(key) && line[key.len()..].trim_start().starts_with(':')),
+        };
+        found.map(|(number, _)| *number)
+    }
+
+    fn line_of(&self, key: &str) -> Option<usize> {
+        self.key_line(key).or(self.line)
+    }
+
+    fn problem(&self, key: &str, message: String) -> ConfigError {
+        ConfigError {
+            key: format!("{}.{}", self.name, key),
+            line: self.line_of(key),
+            message,
+        }
+    }
+
+    fn get(&self, key: &str) -> Option<&'a Yaml> {
+        let value = match self.node {
+            Yaml::Hash(map) => map.get(&Yaml::String(key.to_string())),
+            Yaml::Array(list) => self.order.iter().position(|k| *k == key).and_then(|i| list.get(i)),
+            _ => None,
+        };
+        // an empty value falls back to the default like a missing one
+        value.filter(|v| !v.is_null())
+    }
+
+    // keys that are not part of this section, so a typo does not silently use the default
+    fn check_keys(&self, report: &mut Report) {
+        match self.node {
+            Yaml::Hash(map) => {
+                for key in map.keys() {
+                    let name = key.as_str().map(str::to_string).unwrap_or_else(|| describe(key));
+                    if !self.order.contains(&name.as_str()) {
+                        report.errors.push(self.problem(
+                            &name,
+                            format!("unknown key, expected one of {}", self.order.join(", ")),
+                        ));
+                    }
+                }
+            }
+            Yaml::Array(list) if list.len() > self.order.len() => {
+                report.errors.push(ConfigError {
+                    key: self.name.clone(),
+                    line: self.line,
+                    message: format!(
+                        "{} entries, the list format only has {} ({})",
+                        list.len(),
+                        self.order.len(),
+                        self.order.join(", ")
+                    ),
+                });
+            }
+            _ => {}
+        }
+    }
+
+    // reads a value, recording an error and keeping the default when it has the wrong type
+    fn read<T>(
+        &self,
+        report: &mut Report,
+        key: &str,
+        default: T,
+        expected: &str,
+        convert: impl Fn(&Yaml) -> Option<T>,
+    ) -> T {
+        match self.get(key) {
+            None => default,
+            Some(value) => convert(value).unwrap_or_else(|| {
+                report
+                    .errors
+                    .push(self.problem(key, format!("expected {expected}, found {}", describe(value))));
+                default
+            }),
+        }
+    }
+
+    fn bool(&self, report: &mut Report, key: &str, default: bool) -> bool {
+        self.read(report, key, default, "true or false", Yaml::as_bool)
+    }
+
+    fn f32(&self, report: &mut Report, key: &str, default: f32) -> f32 {
+        self.read(report, key, default, "a number", |value| {
+            value
+                .as_f64()
+                .or_else(|| value.as_i64().map(|v| v as f64))
+                .map(|v| v as f32)
+        })
+    }
+
+    // like f32 but negative values are errors, for durations and weights
+    fn non_negative(&self, report: &mut Report, key: &str, default: f32) -> f32 {
+        let value = self.f32(report, key, default);
+        if value < 0.0 {
+            report
+                .errors
+                .push(self.problem(key, format!("must not be negative, found {value}")));
+            return default;
+        }
+        value
+    }
+
+    fn i32(&self, report: &mut Report, key: &str, default: i32) -> i32 {
+        self.read(report, key, default, "a whole number", |value| {
+            value.as_i64().and_then(|v| i32::try_from(v).ok())
+        })
+    }
+
+    fn strings(&self, report: &mut Report, key: &str, default: Vec<String>) -> Vec<String> {
+        self.read(report, key, default, "a list of names", |value| {
+            value
+                .as_vec()?
+                .iter()
+                .map(|item| item.as_str().map(str::to_string))
+                .collect()
+        })
+    }
+
+    fn distribution(&self, report: &mut Report, key: &str, default: Distribution) -> Distribution {
+        self.read(report, key, default, "uniform or exponential", |value| {
+            Distribution::from_name(value.as_str()?)
+        })
+    }
+
+    fn selection(&self, report: &mut Report, key: &str, default: SelectionMode) -> SelectionMode {
+        self.read(
+            report,
+            key,
+            default,
+            "random, no_repeat, shuffle or playlist",
+            |value| SelectionMode::from_name(value.as_str()?),
+        )
+    }
+
+    fn easing(&self, report: &mut Report, key: &str, default: Easing) -> Easing {
+        self.read(
+            report,
+            key,
+            default,
+            "linear, ease_in, ease_out or ease_in_out",
+            |value| Easing::from_name(value.as_str()?),
+        )
+    }
+
+    // [r, g, b] with values from 0 to 255 or a hex string like "#ff8000"
+    fn color(&self, report: &mut Report, key: &str, default: [u8; 3]) -> [u8; 3] {
+        self.read(
+            report,
+            key,
+            default,
+            "[red, green, blue] from 0 to 255 or a hex color like \"#ff8000\"",
+            |value| match value {
+                Yaml::Array(list) if list.len() == 3 => {
+                    let channel = |i: usize| list[i].as_i64().and_then(|v| u8::try_from(v).ok());
+                    Some([channel(0)?, channel(1)?, channel(2)?])
+                }
+                Yaml::String(text) => {
+                    let hex = text.strip_prefix('#')?;
+                    let channel = |i: usize| u8::from_str_radix(hex.get(i..i + 2)?, 16).ok();
+                    if hex.len() != 6 {
+                        return None;
+                    }
+                    Some([channel(0)?, channel(2)?, channel(4)?])
+                }
+                _ => None,
+            },
+        )
+    }
+
+    fn effect(&self, report: &mut Report, default: &EffectConfig) -> EffectConfig {
+        let r = report;
+        EffectConfig {
+            enabled: self.bool(r, "enabled", default.enabled),
+            weight: self.non_negative(r, "weight", default.weight),
+            duration: self.non_negative(r, "duration", default.duration),
+            color: self.color(r, "color", default.color),
+            alpha: self.non_negative(r, "alpha", default.alpha),
+            fade_in: self.non_negative(r, "fade_in", default.fade_in),
+            fade_out: self.non_negative(r, "fade_out", default.fade_out),
+            easing: self.easing(r, "easing", default.easing),
+        }
+    }
+
+    fn windows(&self, report: &mut Report, key: &str) -> Vec<Window> {
+        let Some(value) = self.get(key) else {
+            return vec![];
+        };
+        let Some(items) = value.as_vec() else {
+            report.errors.push(self.problem(
+                key,
+                format!("expected a list of windows, found {}", describe(value)),
+            ));
+            return vec![];
+        };
+        let mut windows = vec![];
+        for (index, item) in items.iter().enumerate() {
+            match window_from_yaml(item) {
+                Ok(window) => windows.push(window),
+                Err(message) => report
+                    .errors
+                    .push(self.problem(key, format!("entry {}: {message}", index + 1))),
+            }
+        }
+        windows
+    }
+
+    fn monitor(&self, report: &mut Report, key: &str, default: MonitorMode) -> MonitorMode {
+        self.read(
+            report,
+            key,
+            default,
+            "primary, all, random or a monitor index",
+            MonitorMode::from_yaml,
+        )
+    }
+}
+
+// every problem found in a settings file
+#[derive(Debug, Default)]
+pub struct Report {
+    pub errors: Vec<ConfigError>,
+    pub warnings: Vec<ConfigError>,
+}
+
+impl Report {
+    pub fn is_ok(&self) -> bool {
+        self.errors.is_empty()
+    }
+}
+
+impl fmt::Display for Report {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for error in &self.errors {
+            writeln!(f, "error: {error}")?;
+        }
+        for warning in &self.warnings {
+            writeln!(f, "warning: {warning}")?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for Report {}
+
+const SECTIONS: [&str; 7] = [
+    "Settings", "Pong", "Circles", "Avoider", "Effects", "Activity", "Schedule",
+];
+
+const EFFECT_KEYS: &[&str] = &[
+    "enabled", "weight", "duration", "color", "alpha", "fade_in", "fade_out", "easing",
+];
+
+impl Config {
+    // parses as much as possible and collects every error and warning on the way,
+    // values with errors keep their defaults
+    pub fn check(source: &str) -> (Config, Report) {
+        let mut report = Report::default();
+        let defaults = Config::default();
+
+        let documents = match YamlLoader::load_from_str(source) {
+            Ok(documents) => documents,
+            Err(e) => {
+                report.errors.push(ConfigError {
+                    key: "settings.yaml".to_string(),
+                    line: Some(e.marker().line()),
+                    message: e.info().to_string(),
+                });
+                return (defaults, report);
+            }
+        };
+        let root = match documents.first() {
+            Some(root @ Yaml::Hash(_)) => root,
+            Some(Yaml::Null) | None => return (defaults, report),
+            Some(other) => {
+                report.errors.push(ConfigError {
+                    key: "settings.yaml".to_string(),
+                    line: None,
+                    message: format!("expected a mapping of sections, found {}", describe(other)),
+                });
+                return (defaults, report);
+            }
+        };
+
+        if let Some(map) = root.as_hash() {
+            for key in map.keys() {
+                let name = key.as_str().map(str::to_string).unwrap_or_else(|| describe(key));
+                if !SECTIONS.contains(&name.as_str()) {
+                    report.warnings.push(ConfigError {
+                        line: section_line(source, &name),
+                        key: name,
+                        message: format!("unknown section, ignored (expected one of {})", SECTIONS.join(", ")),
+                    });
+                }
+            }
+        }
+
+        let settings = Section::new(
+            root,
+            "Settings",
+            &[
+                "cooldown",
+                "monitor",
+                "selection",
+                "playlist",
+                "min_cooldown",
+                "max_cooldown",
+                "cooldown_distribution",
+                "seed",
+                "tick_rate",
+                "max_catch_up_steps",
+                "adaptive_difficulty",
+                "safe_mode",
+            ],
+            source,
+            &mut report,
+        );
+        let pong = Section::new(
+            root,
+            "Pong",
+            &["enabled", "ball_speed", "paddle_speed", "ai_paddle_speed", "weight"],
+            source,
+            &mut report,
+        );
+        let circles = Section::new(
+            root,
+            "Circles",
+            &["enabled", "min_amount", "max_amount", "time_limit", "weight"],
+            source,
+            &mut report,
+        );
+        let avoider = Section::new(
+            root,
+            "Avoider",
+            &["enabled", "time_limit", "spawn_timer", "weight"],
+            source,
+            &mut report,
+        );
+        let effects = Section::new(root, "Effects", &[
+                "Flash", "Shake", "Melt", "Pixelate", "Flip", "Negative", "Jigsaw", "Invert", "Drift", "Lag",
+                "Dim",
+            ], source, &mut report);
+        let flash = effects.child("Flash", EFFECT_KEYS, &mut report);
+        let shake = effects.child("Shake", EFFECT_KEYS, &mut report);
+        let melt = effects.child("Melt", EFFECT_KEYS, &mut report);
+        let pixelate = effects.child("Pixelate", EFFECT_KEYS, &mut report);
+        let flip = effects.child("Flip", EFFECT_KEYS, &mut report);
+        let negative = effects.child("Negative", EFFECT_KEYS, &mut report);
+        let jigsaw = effects.child("Jigsaw", EFFECT_KEYS, &mut report);
+        let invert = effects.child("Invert", EFFECT_KEYS, &mut report);
+        let drift = effects.child("Drift", EFFECT_KEYS, &mut report);
+        let lag = effects.child("Lag", EFFECT_KEYS, &mut report);
+        let dim = effects.child("Dim", EFFECT_KEYS, &mut report);
+
+        let activity = Section::new(
+            root,
+            "Activity",
+            &["start_idle_limit", "pause_idle_limit"],
+            source,
+            &mut report,
+        );
+        let schedule = Section::new(root, "Schedule", &["allow", "deny"], source, &mut report);
+
+        let r = &mut report;
+        // a fixed cooldown is a range with min and max equal
+        let cooldown = settings.f32(r, "cooldown", defaults.settings.min_cooldown);
+        let config = Config {
+            settings: SettingsConfig {
+                min_cooldown: settings.f32(r, "min_cooldown", cooldown),
+                max_cooldown: settings.f32(r, "max_cooldown", cooldown),
+                cooldown_distribution: settings.distribution(
+                    r,
+                    "cooldown_distribution",
+                    defaults.settings.cooldown_distribution,
+                ),
+                monitor: settings.monitor(r, "monitor", defaults.settings.monitor),
+                selection: settings.selection(r, "selection", defaults.settings.selection),
+                playlist: settings.strings(r, "playlist", defaults.settings.playlist),
+                seed: settings.read(
+                    r,
+                    "seed",
+                    defaults.settings.seed,
+                    "a whole number of at least 0",
+                    |value| value.as_i64().and_then(|v| u64::try_from(v).ok()).map(Some),
+                ),
+                tick_rate: settings.non_negative(r, "tick_rate", defaults.settings.tick_rate),
+                max_catch_up_steps: settings.i32(
+                    r,
+                    "max_catch_up_steps",
+                    defaults.settings.max_catch_up_steps,
+                ),
+                adaptive_difficulty: settings.bool(
+                    r,
+                    "adaptive_difficulty",
+                    defaults.settings.adaptive_difficulty,
+                ),
+                safe_mode: settings.bool(r, "safe_mode", defaults.settings.safe_mode),
+            },
+            pong: PongConfig {
+                enabled: pong.bool(r, "enabled", defaults.pong.enabled),
+                weight: pong.non_negative(r, "weight", defaults.pong.weight),
+                ball_speed: pong.f32(r, "ball_speed", defaults.pong.ball_speed),
+                paddle_speed: pong.f32(r, "paddle_speed", defaults.pong.paddle_speed),
+                ai_paddle_speed: pong.f32(r, "ai_paddle_speed", defaults.pong.ai_paddle_speed),
+            },
+            circles: CirclesConfig {
+                enabled: circles.bool(r, "enabled", defaults.circles.enabled),
+                weight: circles.non_negative(r, "weight", defaults.circles.weight),
+                min_amount: circles.i32(r, "min_amount", defaults.circles.min_amount),
+                max_amount: circles.i32(r, "max_amount", defaults.circles.max_amount),
+                time_limit: circles.non_negative(r, "time_limit", defaults.circles.time_limit),
+            },
+            avoider: AvoiderConfig {
+                enabled: avoider.bool(r, "enabled", defaults.avoider.enabled),
+                weight: avoider.non_negative(r, "weight", defaults.avoider.weight),
+                time_limit: avoider.non_negative(r, "time_limit", defaults.avoider.time_limit),
+                spawn_timer: avoider.non_negative(r, "spawn_timer", defaults.avoider.spawn_timer),
+            },
+            effects: EffectsConfig {
+                flash: flash.effect(r, &defaults.effects.flash),
+                shake: shake.effect(r, &defaults.effects.shake),
+                melt: melt.effect(r, &defaults.effects.melt),
+                pixelate: pixelate.effect(r, &defaults.effects.pixelate),
+                flip: flip.effect(r, &defaults.effects.flip),
+                negative: negative.effect(r, &defaults.effects.negative),
+                jigsaw: jigsaw.effect(r, &defaults.effects.jigsaw),
+                invert: invert.effect(r, &defaults.effects.invert),
+                drift: drift.effect(r, &defaults.effects.drift),
+                lag: lag.effect(r, &defaults.effects.lag),
+                dim: dim.effect(r, &defaults.effects.dim),
+            },
+            activity: ActivityConfig {
+                start_idle_limit: activity.non_negative(
+                    r,
+                    "start_idle_limit",
+                    defaults.activity.start_idle_limit,
+                ),
+                pause_idle_limit: activity.non_negative(
+                    r,
+                    "pause_idle_limit",
+                    defaults.activity.pause_idle_limit,
+                ),
+            },
+            schedule: Schedule {
+                allow: schedule.windows(r, "allow"),
+                deny: schedule.windows(r, "deny"),
+            },
+        };
+
+        for (key, value) in [
+            ("min_cooldown", config.settings.min_cooldown),
+            ("max_cooldown", config.settings.max_cooldown),
+        ] {
+            // point at the plain cooldown key when the range was not written out
+            let key = if settings.get(key).is_some() { key } else { "cooldown" };
+            if value < 0.0 {
+                report.warnings.push(settings.problem(
+                    key,
+                    format!("{value} is negative, games will start back to back"),
+                ));
+                break;
+            }
+        }
+        if config.settings.min_cooldown > config.settings.max_cooldown {
+            report.warnings.push(settings.problem(
+                "min_cooldown",
+                format!(
+                    "{} is larger than max_cooldown {}, the two are swapped",
+                    config.settings.min_cooldown, config.settings.max_cooldown
+                ),
+            ));
+        }
+        if config.settings.tick_rate == 0.0 {
+            report.errors.push(settings.problem("tick_rate", "must be above 0".to_string()));
+        }
+        if config.settings.max_catch_up_steps < 1 {
+            report.errors.push(settings.problem(
+                "max_catch_up_steps",
+                format!("must be at least 1, found {}", config.settings.max_catch_up_steps),
+            ));
+        }
+        if config.circles.max_amount < 1 {
+            report.errors.push(circles.problem(
+                "max_amount",
+                format!("must be at least 1, found {}", config.circles.max_amount),
+            ));
+        }
+        if config.circles.min_amount > config.circles.max_amount {
+            report.warnings.push(circles.problem(
+                "min_amount",
+                format!(
+                    "{} is larger than max_amount {}, rounds will use {}",
+                    config.circles.min_amount, config.circles.max_amount, config.circles.max_amount
+                ),
+            ));
+        }
+        if config.avoider.spawn_timer == 0.0 {
+            report.warnings.push(avoider.problem(
+                "spawn_timer",
+                "is 0, an enemy spawns every frame".to_string(),
+            ));
+        }
+        for (section, effect) in [
+            (&flash, &config.effects.flash),
+            (&shake, &config.effects.shake),
+            (&melt, &config.effects.melt),
+            (&pixelate, &config.effects.pixelate),
+            (&flip, &config.effects.flip),
+            (&negative, &config.effects.negative),
+            (&jigsaw, &config.effects.jigsaw),
+            (&invert, &config.effects.invert),
+            (&drift, &config.effects.drift),
+            (&lag, &config.effects.lag),
+            (&dim, &config.effects.dim),
+        ] {
+            if effect.alpha > 1.0 {
+                report.errors.push(section.problem(
+                    "alpha",
+                    format!("must be between 0.0 and 1.0, found {}", effect.alpha),
+                ));
+            }
+            if effect.fade_in + effect.fade_out > effect.duration {
+                report.warnings.push(section.problem(
+                    "fade_in",
+                    format!(
+                        "fade_in and fade_out together are longer than duration {}, the effect never reaches full strength",
+                        effect.duration
+                    ),
+                ));
+            }
+        }
+        let effect_list = [
+            &config.effects.flash,
+            &config.effects.shake,
+            &config.effects.melt,
+            &config.effects.pixelate,
+            &config.effects.flip,
+            &config.effects.negative,
+            &config.effects.jigsaw,
+            &config.effects.invert,
+            &config.effects.drift,
+            &config.effects.lag,
+            &config.effects.dim,
+        ];
+        if effect_list.iter().all(|effect| !effect.enabled) {
+            report.warnings.push(effects.problem(
+                "Flash",
+                "every effect is disabled, losing a round has no effect".to_string(),
+            ));
+        }
+        let enabled = [
+            ("Pong", config.pong.enabled),
+            ("Circles", config.circles.enabled),
+            ("Avoider", config.avoider.enabled),
+        ];
+        for name in &config.settings.playlist {
+            match enabled.iter().find(|(game, _)| *game == name.as_str()) {
+                None => report.errors.push(settings.problem(
+                    "playlist",
+                    format!("unknown game \"{name}\", expected Pong, Circles or Avoider"),
+                )),
+                Some((_, false)) => report.warnings.push(settings.problem(
+                    "playlist",
+                    format!("{name} is disabled and will be skipped"),
+                )),
+                Some(_) => {}
+            }
+        }
+        if config.settings.selection == SelectionMode::Playlist && config.settings.playlist.is_empty() {
+            report.warnings.push(settings.problem(
+                "playlist",
+                "is empty, the playlist selection will play the games in file order".to_string(),
+            ));
+        }
+        let now = chrono::Local::now().naive_local();
+        if !config.schedule.allows(now) && config.schedule.next_open(now).is_none() {
+            report.warnings.push(schedule.problem(
+                "allow",
+                "no time in the coming week allows games, nothing will ever be played".to_string(),
+            ));
+        }
+        // the overlay refuses to start without a game, so this is an error and not a warning
+        if !config.pong.enabled && !config.circles.enabled && !config.avoider.enabled {
+            report.errors.push(ConfigError {
+                key: "settings.yaml".to_string(),
+                line: None,
+                message: "every game is disabled, nothing will ever be played".to_string(),
+            });
+        }
+
+        (config, report)
+    }
+
+    // reads the settings file, writing the default one if missing
+    pub fn load(path: &Path) -> Result<Config, Box<dyn std::error::Error>> {
+        let contents = if !path.exists() {
+            println!("no settings file. creating one");
+            std::fs::write(path, DEFAULT_SETTINGS)?;
+            DEFAULT_SETTINGS.to_string()
+        } else {
+            println!("settings file found");
+            std::fs::read_to_string(path)?
+        };
+
+        let (config, report) = Config::check(&contents);
+        if !report.is_ok() {
+            return Err(Box::new(report));
+        }
+        for warning in &report.warnings {
+            println!("settings warning: {warning}");
+        }
+        Ok(config)
+    }
+}
+
+// polls the modification time of the settings file
+pub struct Watcher {
+    path: PathBuf,
+    modified: Option<SystemTime>,
+    last_poll: SystemTime,
+}
+
+fn modified(path: &Path) -> Option<SystemTime> {
+    std::fs::metadata(path).and_then(|m| m.modified()).ok()
+}
+
+impl Watcher {
+    const POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+    pub fn new(path: PathBuf) -> Watcher {
+        Watcher {
+            modified: modified(&path),
+            path,
+            last_poll: SystemTime::now(),
+        }
+    }
+
+    // None while the file is unchanged, otherwise the newly parsed config
+    pub fn poll(&mut self) -> Option<Result<Config, Box<dyn std::error::Error>>> {
+        if self.last_poll.elapsed().unwrap_or_default() < Self::POLL_INTERVAL {
+            return None;
+        }
+        self.last_poll = SystemTime::now();
+
+        let modified = modified(&self.path);
+        if modified == self.modified {
+            return None;
+        }
+        self.modified = modified;
+
+        let contents = match std::fs::read_to_string(&self.path) {
+            Ok(contents) => contents,
+            Err(e) => return Some(Err(e.into())),
+        };
+        let (config, report) = Config::check(&contents);
+        if !report.is_ok() {
+            return Some(Err(Box::new(report)));
+        }
+        for warning in &report.warnings {
+            println!("settings warning: {warning}");
+        }
+        Some(Ok(config))
+    }
+}
+
+// settings.yaml next to the executable
+pub fn settings_path() -> Result<PathBuf, Box<dyn std::error::Error>> {
+    let exe_path = std::env::current_exe()?;
+    let exe_dir = exe_path
+        .parent()
+        .ok_or("Could not get executable directory")?;
+    Ok(exe_dir.join("settings.yaml"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(source: &str) -> Config {
+        let (config, report) = Config::check(source);
+        assert!(report.is_ok(), "{report}");
+        config
+    }
+
+    fn errors(source: &str) -> Vec<ConfigError> {
+        Config::check(source).1.errors
+    }
+
+    #[test]
+    fn named_keys_override_only_themselves() {
+        let config = parse(
+            "Settings:
+  cooldown: 60.0
+  monitor: all
+Pong:
+  ball_speed: 400.0
+Circles:
+  min_amount: 2
+",
+        );
+        let defaults = Config::default();
+        assert_eq!(config.settings.min_cooldown, 60.0);
+        assert_eq!(config.settings.max_cooldown, 60.0);
+        assert_eq!(config.settings.monitor, MonitorMode::All);
+        assert_eq!(config.settings.selection, defaults.settings.selection);
+        assert_eq!(config.pong.ball_speed, 400.0);
+        assert_eq!(config.pong.paddle_speed, defaults.pong.paddle_speed);
+        assert_eq!(config.circles.min_amount, 2);
+        assert_eq!(config.circles.max_amount, defaults.circles.max_amount);
+    }
+
+    #[test]
+    fn default_settings_have_no_problems() {
+        let (_, report) = Config::check(DEFAULT_SETTINGS);
+        assert!(report.errors.is_empty() && report.warnings.is_empty(), "{report}");
+    }
+
+    #[test]
+    fn legacy_lists_follow_the_key_order() {
+        let config = parse(
+            "Settings: [60.0, all, shuffle]
+Pong: [false, 400.0]
+Circles:
+  - true
+  - 3
+",
+        );
+        assert_eq!(config.settings.min_cooldown, 60.0);
+        assert_eq!(config.settings.monitor, MonitorMode::All);
+        assert_eq!(config.settings.selection, SelectionMode::Shuffle);
+        assert!(!config.pong.enabled);
+        assert_eq!(config.pong.ball_speed, 400.0);
+        assert_eq!(config.pong.paddle_speed, Config::default().pong.paddle_speed);
+        assert_eq!(config.circles.min_amount, 3);
+    }
+
+    #[test]
+    fn errors_name_the_key_and_its_line() {
+        let errors = errors(
+            "Settings:
+  cooldown: 60.0
+
+Pong:
+  enabled: true
+  ball_speed: fast
+",
+        );
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].key, "Pong.ball_speed");
+        assert_eq!(errors[0].line, Some(6));
+        assert_eq!(
+            errors[0].to_string(),
+            "Pong.ball_speed (line 6): expected a number, found \"fast\""
+        );
+    }
+
+    #[test]
+    fn legacy_list_errors_point_at_the_entry() {
+        let errors = errors(
+            "Pong:
+  - true
+  - fast
+",
+        );
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].key, "Pong.ball_speed");
+        assert_eq!(errors[0].line, Some(3));
+    }
+
+    #[test]
+    fn nested_effect_errors_use_the_dotted_path() {
+        let errors = errors(
+            "Effects:
+  Flash:
+    duration: 5.0
+    easing: bouncy
+",
+        );
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].key, "Effects.Flash.easing");
+        assert_eq!(errors[0].line, Some(4));
+    }
+
+    #[test]
+    fn malformed_document_keeps_the_defaults() {
+        let (config, report) = Config::check(
+            "Settings:
+  cooldown: 60.0
+  monitor: [all
+Pong:
+  enabled: true
+",
+        );
+        assert_eq!(report.errors.len(), 1);
+        assert_eq!(report.errors[0].key, "settings.yaml");
+        assert_eq!(report.errors[0].line, Some(4));
+        assert_eq!(config.settings.min_cooldown, Config::default().settings.min_cooldown);
+    }
+
+    #[test]
+    fn unknown_keys_are_errors_and_unknown_sections_warnings() {
+        let (config, report) = Config::check(
+            "Pong:
+  ball_sped: 400.0
+Tetris:
+  enabled: true
+",
+        );
+        assert_eq!(report.errors.len(), 1);
+        assert_eq!(report.errors[0].key, "Pong.ball_sped");
+        assert_eq!(report.errors[0].line, Some(2));
+        assert!(report.errors[0].message.starts_with("unknown key"));
+        assert_eq!(report.warnings.len(), 1);
+        assert_eq!(report.warnings[0].key, "Tetris");
+        assert_eq!(report.warnings[0].line, Some(3));
+        assert_eq!(config.pong.ball_speed, Config::default().pong.ball_speed);
+    }
+
+    #[test]
+    fn report_collects_every_problem() {
+        let (_, report) = Config::check(
+            "Settings:
+  min_cooldown: 300.0
+  max_cooldown: 60.0
+  tick_rate: 0
+Circles:
+  max_amount: 0
+Effects:
+  Flash:
+    alpha: 2.0
+",
+        );
+        let keys = |list: &[ConfigError]| list.iter().map(|e| e.key.clone()).collect::<Vec<_>>();
+        assert_eq!(
+            keys(&report.errors),
+            ["Settings.tick_rate", "Circles.max_amount", "Effects.Flash.alpha"]
+        );
+        assert_eq!(
+            keys(&report.warnings),
+            ["Settings.min_cooldown", "Circles.min_amount"]
+        );
+        let text = report.to_string();
+        assert!(text.starts_with("error: Settings.tick_rate (line 4): must be above 0\n"));
+        assert!(text.contains("warning: Settings.min_cooldown (line 2): 300 is larger than max_cooldown 60"));
+    }
+
+    #[test]
+    fn every_game_disabled_is_an_error() {
+        let (_, report) = Config::check(
+            "Pong: [false]
+Circles: [false]
+Avoider: [false]
+",
+        );
+        assert!(!report.is_ok());
+        assert_eq!(report.errors[0].key, "settings.yaml");
+    }
+
+    #[test]
+    fn warnings_alone_still_load() {
+        let (config, report) = Config::check(
+            "Avoider:
+  spawn_timer: 0
+",
+        );
+        assert!(report.is_ok());
+        assert_eq!(report.warnings.len(), 1);
+        assert_eq!(config.avoider.spawn_timer, 0.0);
+    }
+}
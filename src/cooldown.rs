@@ -0,0 +1,99 @@
+use rand::Rng;
+use std::time::Duration;
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Distribution {
+    // every interval between min and max is equally likely
+    Uniform,
+    // short waits are common and long ones rare, like arrivals of a poisson process
+    Exponential,
+}
+
+impl Distribution {
+    pub fn from_name(name: &str) -> Option<Distribution> {
+        match name {
+            "uniform" => Some(Distribution::Uniform),
+            "exponential" => Some(Distribution::Exponential),
+            _ => None,
+        }
+    }
+}
+
+// time between games, drawn fresh after every game
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Cooldown {
+    min: f32,
+    max: f32,
+    distribution: Distribution,
+}
+
+impl Cooldown {
+    pub fn new(min: f32, max: f32, distribution: Distribution) -> Cooldown {
+        // negative values only get a warning in the config, treat them as no cooldown
+        let (min, max) = (min.max(0.0), max.max(0.0));
+        Cooldown {
+            min: min.min(max),
+            max: min.max(max),
+            distribution,
+        }
+    }
+
+    pub fn next(&self, rng: &mut impl Rng) -> Duration {
+        let range = self.max - self.min;
+        if range <= 0.0 {
+            return Duration::from_secs_f32(self.min);
+        }
+        let roll: f32 = rng.random();
+        let extra = match self.distribution {
+            Distribution::Uniform => roll * range,
+            Distribution::Exponential => {
+                // exponential cut off at max, mean of the untruncated curve is a third of the range
+                let mean = range / 3.0;
+                -mean * (1.0 - roll * (1.0 - (-range / mean).exp())).ln()
+            }
+        };
+        Duration::from_secs_f32(self.min + extra.clamp(0.0, range))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::SeedableRng;
+    use rand::rngs::StdRng;
+
+    fn draws(cooldown: Cooldown, count: usize) -> Vec<f32> {
+        let mut rng = StdRng::seed_from_u64(42);
+        (0..count).map(|_| cooldown.next(&mut rng).as_secs_f32()).collect()
+    }
+
+    #[test]
+    fn exponential_stays_within_the_range() {
+        let draws = draws(Cooldown::new(60.0, 300.0, Distribution::Exponential), 2000);
+        assert!(draws.iter().all(|&d| (60.0..=300.0).contains(&d)));
+        // truncated at max, most waits are still on the short side
+        let short = draws.iter().filter(|&&d| d < 180.0).count();
+        assert!(short > draws.len() * 3 / 4, "{short}");
+    }
+
+    #[test]
+    fn uniform_stays_within_the_range() {
+        let draws = draws(Cooldown::new(10.0, 20.0, Distribution::Uniform), 2000);
+        assert!(draws.iter().all(|&d| (10.0..=20.0).contains(&d)));
+        let mean = draws.iter().sum::<f32>() / draws.len() as f32;
+        assert!((mean - 15.0).abs() < 0.5, "{mean}");
+    }
+
+    #[test]
+    fn swapped_bounds_are_put_in_order() {
+        let draws = draws(Cooldown::new(20.0, 10.0, Distribution::Uniform), 200);
+        assert!(draws.iter().all(|&d| (10.0..=20.0).contains(&d)));
+    }
+
+    #[test]
+    fn negative_values_clamp_to_zero() {
+        assert_eq!(draws(Cooldown::new(-5.0, -1.0, Distribution::Uniform), 10), [0.0; 10]);
+        let draws = draws(Cooldown::new(-5.0, 2.0, Distribution::Exponential), 200);
+        assert!(draws.iter().all(|&d| (0.0..=2.0).contains(&d)));
+    }
+}
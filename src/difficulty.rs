@@ -0,0 +1,167 @@
+use crate::game_handler::GameOutcome;
+use serde_json::{Map, Value};
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+
+// how far a round moves the rating
+const STEP: f32 = 0.1;
+
+// a difficulty level per game from 0.0 (the configured values) to 1.0 (hardest),
+// rising on wins and falling on losses
+#[derive(Clone, Debug, Default)]
+pub struct Ratings {
+    levels: BTreeMap<String, f32>,
+    // saved here after every change, never saved when None
+    path: Option<PathBuf>,
+}
+
+impl Ratings {
+    // all games at level 0.0, not saved
+    pub fn new() -> Ratings {
+        Ratings {
+            levels: BTreeMap::new(),
+            path: None,
+        }
+    }
+
+    // ratings from a json object like {"Pong": 0.3}, unknown values are skipped
+    pub fn from_json(text: &str) -> Result<Ratings, serde_json::Error> {
+        let value: Value = serde_json::from_str(text)?;
+        let levels = value
+            .as_object()
+            .map(|map| {
+                map.iter()
+                    .filter_map(|(game, level)| Some((game.clone(), (level.as_f64()? as f32).clamp(0.0, 1.0))))
+                    .collect()
+            })
+            .unwrap_or_default();
+        Ok(Ratings {
+            levels,
+            path: None,
+        })
+    }
+
+    // ratings saved by an earlier run, a missing or broken file starts over
+    pub fn load(path: PathBuf) -> Ratings {
+        let mut ratings = match std::fs::read_to_string(&path) {
+            Ok(text) => Ratings::from_json(&text).unwrap_or_else(|e| {
+                println!("difficulty file {} is broken, starting over: {e}", path.display());
+                Ratings::new()
+            }),
+            Err(_) => Ratings::new(),
+        };
+        ratings.path = Some(path);
+        ratings
+    }
+
+    pub fn to_json(&self) -> String {
+        let map: Map<String, Value> = self
+            .levels
+            .iter()
+            .map(|(game, level)| (game.clone(), Value::from(*level)))
+            .collect();
+        Value::Object(map).to_string()
+    }
+
+    pub fn level(&self, game: &str) -> f32 {
+        self.levels.get(game).copied().unwrap_or(0.0)
+    }
+
+    // moves the rating of game after a round, aborted rounds do not count
+    pub fn update(&mut self, game: &str, outcome: &GameOutcome) {
+        let level = self.level(game);
+        let change = match outcome {
+            GameOutcome::Won(_) => STEP,
+            // a narrow loss drops half as much as a loss without any progress
+            GameOutcome::Lost(_) | GameOutcome::TimedOut(_) => -STEP * (0.5 + 0.5 * outcome.severity()),
+            GameOutcome::Aborted(_) => return,
+        };
+        let new_level = (level + change).clamp(0.0, 1.0);
+        println!("{game} difficulty: {level:.2} -> {new_level:.2}");
+        self.levels.insert(game.to_string(), new_level);
+
+        if let Some(path) = &self.path
+            && let Err(e) = std::fs::write(path, self.to_json())
+        {
+            println!("could not save the difficulty to {}: {e}", path.display());
+        }
+    }
+}
+
+// the difficulty file next to the executable
+pub fn difficulty_path() -> Result<PathBuf, Box<dyn std::error::Error>> {
+    let exe_path = std::env::current_exe()?;
+    let exe_dir = exe_path
+        .parent()
+        .ok_or("Could not get executable directory")?;
+    Ok(exe_dir.join("difficulty.json"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game_handler::RoundStats;
+    use std::time::Duration;
+
+    fn stats(progress: f32) -> RoundStats {
+        RoundStats {
+            score: 0,
+            duration: Duration::from_secs(5),
+            progress,
+            metadata: vec![],
+        }
+    }
+
+    fn temp_path(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("difficulty-test-{}-{name}.json", std::process::id()))
+    }
+
+    #[test]
+    fn wins_and_losses_step_the_level_within_bounds() {
+        let mut ratings = Ratings::new();
+        ratings.update("Pong", &GameOutcome::Won(stats(1.0)));
+        assert!((ratings.level("Pong") - 0.1).abs() < 1e-6);
+        // a loss without progress drops a full step, clamped at 0
+        ratings.update("Pong", &GameOutcome::Lost(stats(0.0)));
+        ratings.update("Pong", &GameOutcome::Lost(stats(0.0)));
+        assert_eq!(ratings.level("Pong"), 0.0);
+
+        for _ in 0..15 {
+            ratings.update("Circles", &GameOutcome::Won(stats(1.0)));
+        }
+        assert_eq!(ratings.level("Circles"), 1.0);
+        // a narrow loss drops half a step, an aborted round nothing
+        ratings.update("Circles", &GameOutcome::TimedOut(stats(1.0)));
+        assert!((ratings.level("Circles") - 0.95).abs() < 1e-6);
+        ratings.update("Circles", &GameOutcome::Aborted(stats(0.0)));
+        assert!((ratings.level("Circles") - 0.95).abs() < 1e-6);
+        assert_eq!(ratings.level("Avoider"), 0.0);
+    }
+
+    #[test]
+    fn json_round_trip_skips_and_clamps_bad_levels() {
+        let ratings = Ratings::from_json(r#"{"Pong": 0.25, "Circles": 7, "Avoider": "hard"}"#).unwrap();
+        assert_eq!(ratings.level("Pong"), 0.25);
+        assert_eq!(ratings.level("Circles"), 1.0);
+        assert_eq!(ratings.level("Avoider"), 0.0);
+        assert_eq!(ratings.to_json(), r#"{"Circles":1.0,"Pong":0.25}"#);
+        assert!(Ratings::from_json("not json").is_err());
+    }
+
+    #[test]
+    fn missing_or_broken_file_starts_over() {
+        let path = temp_path("missing");
+        let _ = std::fs::remove_file(&path);
+        let mut ratings = Ratings::load(path.clone());
+        assert_eq!(ratings.level("Pong"), 0.0);
+        // saved on the first change
+        ratings.update("Pong", &GameOutcome::Won(stats(1.0)));
+        assert!((Ratings::load(path.clone()).level("Pong") - 0.1).abs() < 1e-6);
+        std::fs::remove_file(&path).unwrap();
+
+        let path = temp_path("broken");
+        std::fs::write(&path, "{\"Pong\": 0.5").unwrap();
+        assert_eq!(Ratings::load(path.clone()).level("Pong"), 0.0);
+        std::fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,76 @@
+use std::rc::Rc;
+
+use crate::{loss_handler::EffectContext, platform::Screenshot};
+
+fn is_black(screen: &Screenshot) -> bool {
+    screen.average()[..3].iter().all(|&channel| channel <= 2)
+}
+
+// the desktop picture effects work from, taken once when the effect starts
+#[derive(Default)]
+pub struct ScreenCapture {
+    screen: Option<Rc<Screenshot>>,
+}
+
+impl ScreenCapture {
+    pub fn new() -> ScreenCapture {
+        ScreenCapture { screen: None }
+    }
+
+    // takes a new picture of the desktop behind the overlay, false if there is none
+    pub fn take(&mut self, ctx: &EffectContext) -> bool {
+        self.screen = match ctx.desktop.capture(ctx.window) {
+            // what X11 gives under some compositors, shown it would only black out the screen
+            Some(screen) if is_black(&screen) => {
+                println!("the screen capture came back black, the effect runs without it");
+                None
+            }
+            Some(screen) => Some(Rc::new(screen)),
+            None => {
+                println!("could not capture the screen");
+                None
+            }
+        };
+        self.screen.is_some()
+    }
+
+    pub fn get(&self) -> Option<&Rc<Screenshot>> {
+        self.screen.as_ref()
+    }
+
+    // screenshots are large, effects drop theirs when they finish
+    pub fn clear(&mut self) {
+        self.screen = None;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::platform::headless::Headless;
+    use rand::SeedableRng;
+    use rand::rngs::StdRng;
+    use raylib::prelude::*;
+
+    fn take(desktop: &Headless) -> bool {
+        let mut rng = StdRng::seed_from_u64(0);
+        let ctx = EffectContext {
+            severity: 1.0,
+            rng: &mut rng,
+            desktop,
+            window: Rectangle::new(0.0, 0.0, 64.0, 32.0),
+        };
+        ScreenCapture::new().take(&ctx)
+    }
+
+    #[test]
+    fn black_capture_is_dropped() {
+        let mut desktop = Headless::new((64, 32));
+        assert!(take(&desktop));
+        desktop.set_screen(Screenshot::new(64, 32, [0, 0, 0, 255].repeat(64 * 32)));
+        assert!(!take(&desktop));
+        // a dark desktop is still a desktop
+        desktop.set_screen(Screenshot::new(64, 32, [20, 24, 30, 255].repeat(64 * 32)));
+        assert!(take(&desktop));
+    }
+}
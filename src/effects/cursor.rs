@@ -0,0 +1,295 @@
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use rand::prelude::*;
+use raylib::prelude::*;
+
+use crate::{
+    canvas::Canvas,
+    config::EffectConfig,
+    effects::{Easing, fade},
+    loss_handler::{Effect, EffectContext},
+    platform::Platform,
+    util::Timer,
+};
+
+// no effect may hold the cursor longer than this, whatever its duration says
+const HARD_LIMIT: Duration = Duration::from_secs(30);
+// pixels per second the cursor slides on its own after the worst loss
+const DRIFT_SPEED: f32 = 150.0;
+// radians per second the drift direction wanders
+const DRIFT_TURN: f32 = 1.5;
+// seconds the cursor needs to catch up with the mouse after the worst loss
+const LAG: f32 = 0.4;
+
+// until when the cursor may be moved by an effect. a deadline on the wall clock,
+// so a stalled or confused frame loop can never keep the cursor captive
+static LEASH: Mutex<Option<Instant>> = Mutex::new(None);
+
+fn leash() -> std::sync::MutexGuard<'static, Option<Instant>> {
+    // a panic while it was locked must not keep the cursor captive
+    LEASH.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+// lets effects move the cursor for at most duration
+fn hold(duration: Duration) {
+    *leash() = Some(Instant::now() + duration.min(HARD_LIMIT));
+}
+
+// gives the mouse back at once. safe to call from any thread, at exit, on F8 and in the panic hook
+pub fn release() {
+    *leash() = None;
+}
+
+fn held() -> bool {
+    leash().is_some_and(|deadline| Instant::now() < deadline)
+}
+
+// the only way effects move the cursor, does nothing once the leash is released or ran out
+fn warp(desktop: &dyn Platform, pos: Vector2) {
+    if held() {
+        desktop.warp_cursor(pos);
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Sabotage {
+    // the cursor moves the opposite way of the mouse
+    Invert,
+    // the cursor slides away on its own
+    Drift,
+    // the cursor trails behind the mouse
+    Lag,
+}
+
+impl Sabotage {
+    fn message(self) -> &'static str {
+        match self {
+            Sabotage::Invert => "your mouse is upside down",
+            Sabotage::Drift => "your mouse is slipping",
+            Sabotage::Lag => "your mouse is tired",
+        }
+    }
+}
+
+// messes with the cursor instead of the screen. the cursor is only ever moved, no
+// mouse setting is touched, so normal input is back as soon as the moving stops
+pub struct Cursor {
+    sabotage: Sabotage,
+    effect_size: Rectangle,
+    duration: Timer,
+    // duration at full severity
+    max_duration: Duration,
+    color: Color,
+    alpha: f32,
+    fade_in: f32,
+    fade_out: f32,
+    easing: Easing,
+    // overlay window in desktop coordinates, the cursor is kept inside it
+    window: Rectangle,
+    strength: f32,
+    // where the cursor was left last frame, the difference to now is what the mouse did
+    left_at: Option<Vector2>,
+    // where the mouse would have taken the cursor without the effect
+    intended: Vector2,
+    drift_angle: f32,
+    drift_turn: f32,
+}
+
+impl Cursor {
+    pub fn new(sabotage: Sabotage, screen_size: (i32, i32), config: &EffectConfig) -> Cursor {
+        let duration = Duration::from_secs_f32(config.duration);
+        Cursor {
+            sabotage,
+            effect_size: rrect(0, 0, screen_size.0, screen_size.1),
+            duration: Timer::new(duration),
+            max_duration: duration,
+            color: Color::new(config.color[0], config.color[1], config.color[2], 255),
+            alpha: config.alpha,
+            fade_in: config.fade_in,
+            fade_out: config.fade_out,
+            easing: config.easing,
+            window: Rectangle::new(0.0, 0.0, screen_size.0 as f32, screen_size.1 as f32),
+            strength: 1.0,
+            left_at: None,
+            intended: Vector2::zero(),
+            drift_angle: 0.0,
+            drift_turn: 0.0,
+        }
+    }
+
+    fn clamp(&self, pos: Vector2) -> Vector2 {
+        Vector2::new(
+            pos.x.clamp(self.window.x, self.window.x + self.window.width - 1.0),
+            pos.y.clamp(self.window.y, self.window.y + self.window.height - 1.0),
+        )
+    }
+
+    // the cursor position for this frame given where the mouse put it
+    fn steer(&mut self, cursor: Vector2, amount: f32, delta_time: f32) -> Vector2 {
+        let Some(left_at) = self.left_at else {
+            self.intended = cursor;
+            return cursor;
+        };
+        let moved = cursor - left_at;
+        self.intended = self.clamp(self.intended + moved);
+        match self.sabotage {
+            // half inverted would be a frozen cursor, so it is all or nothing
+            Sabotage::Invert if amount >= 0.5 => left_at - moved,
+            Sabotage::Invert => cursor,
+            Sabotage::Drift => {
+                self.drift_angle += self.drift_turn * delta_time;
+                let direction = Vector2::new(self.drift_angle.cos(), self.drift_angle.sin());
+                cursor + direction * (DRIFT_SPEED * amount * delta_time)
+            }
+            Sabotage::Lag => {
+                let lag = LAG * amount;
+                let follow = if lag > 0.0 { 1.0 - (-delta_time / lag).exp() } else { 1.0 };
+                left_at + (self.intended - left_at) * follow
+            }
+        }
+    }
+}
+
+impl Effect for Cursor {
+    fn on_start(&mut self, ctx: &mut EffectContext) {
+        let severity = ctx.severity.clamp(0.0, 1.0);
+        self.duration.set_duration(self.max_duration.mul_f32(0.4 + 0.6 * severity));
+        self.strength = 0.4 + 0.6 * severity;
+        self.window = ctx.window;
+        self.left_at = None;
+        self.drift_angle = ctx.rng.random_range(0.0..std::f32::consts::TAU);
+        self.drift_turn = ctx.rng.random_range(-DRIFT_TURN..DRIFT_TURN);
+        // a little longer than the effect so a slow last frame still ends it cleanly
+        hold(self.duration.duration() + Duration::from_secs(1));
+    }
+
+    fn update(&mut self, desktop: &dyn Platform, delta_time: f32) {
+        let amount = self.strength
+            * fade(
+                self.duration.elapsed().as_secs_f32(),
+                self.duration.duration().as_secs_f32(),
+                self.fade_in,
+                self.fade_out,
+                self.easing,
+            );
+        let cursor = desktop.mouse_pos();
+        let target = self.steer(cursor, amount, delta_time);
+        let target = self.clamp(target);
+        if target != cursor {
+            warp(desktop, target);
+        }
+        self.left_at = Some(target);
+    }
+
+    fn draw(&mut self, d: &mut dyn Canvas, delta_time: f32) {
+        self.duration.update(delta_time);
+        let color = Color { a: (self.alpha * 255.0) as u8, ..self.color };
+        let text = format!("{:?}", self.duration.time_left());
+        let center = (self.effect_size.x * 2.0 + self.effect_size.width) as i32 / 2;
+        let x = center - d.measure_text(&text, 50) / 2;
+        d.draw_text(&text, x, self.effect_size.y as i32 + 10, 50, color);
+        let message = self.sabotage.message();
+        let x = center - d.measure_text(message, 30) / 2;
+        d.draw_text(message, x, self.effect_size.y as i32 + 70, 30, color);
+    }
+
+    fn stop(&mut self, desktop: &dyn Platform) {
+        // a lagging cursor is put where the mouse meant it to be, nothing the player did is lost
+        if self.sabotage == Sabotage::Lag && self.left_at.is_some() {
+            warp(desktop, self.intended);
+        }
+        self.left_at = None;
+        release();
+    }
+
+    fn is_finished(&mut self) -> bool {
+        if self.duration.is_finished() {
+            self.duration.reset();
+            true
+        } else {
+            false
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::EffectsConfig;
+    use crate::platform::headless::Headless;
+    use rand::SeedableRng;
+    use rand::rngs::StdRng;
+
+    // the leash is shared by the whole process, these tests must not hold it at the same time
+    static SERIAL: Mutex<()> = Mutex::new(());
+
+    const SCREEN: (i32, i32) = (320, 200);
+
+    fn start(sabotage: Sabotage, config: &EffectConfig, desktop: &Headless) -> Cursor {
+        let mut cursor = Cursor::new(sabotage, SCREEN, config);
+        let mut rng = StdRng::seed_from_u64(0);
+        cursor.on_start(&mut EffectContext {
+            severity: 1.0,
+            rng: &mut rng,
+            desktop,
+            window: rrect(0, 0, SCREEN.0, SCREEN.1),
+        });
+        cursor.update(desktop, 1.0 / 60.0);
+        cursor
+    }
+
+    // the mouse moves by offset and the effect gets a frame to answer
+    fn nudge(cursor: &mut Cursor, desktop: &mut Headless, offset: Vector2) -> Vector2 {
+        desktop.set_mouse_pos(desktop.mouse_pos() + offset);
+        cursor.update(desktop, 1.0 / 60.0);
+        desktop.mouse_pos()
+    }
+
+    #[test]
+    fn release_lets_go_of_a_hold() {
+        let _serial = SERIAL.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        hold(Duration::from_secs(5));
+        assert!(held());
+        release();
+        assert!(!held());
+        assert!(leash().is_none());
+    }
+
+    #[test]
+    fn hard_limit_ends_the_steering() {
+        let _serial = SERIAL.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        hold(Duration::from_secs(3600));
+        let deadline = leash().unwrap();
+        assert!(deadline <= Instant::now() + HARD_LIMIT);
+
+        let mut desktop = Headless::new(SCREEN);
+        let mut cursor = start(Sabotage::Invert, &EffectsConfig::default().invert, &desktop);
+        let center = desktop.mouse_pos();
+        let step = Vector2::new(10.0, 0.0);
+        assert_eq!(nudge(&mut cursor, &mut desktop, step), center - step);
+
+        // as if the limit ran out while the effect still thinks it has time left
+        *leash() = Some(Instant::now());
+        let left_at = desktop.mouse_pos();
+        assert_eq!(nudge(&mut cursor, &mut desktop, step), left_at + step);
+        release();
+    }
+
+    #[test]
+    fn lag_stops_where_the_mouse_meant_to_go() {
+        let _serial = SERIAL.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        let mut config = EffectsConfig::default().lag;
+        config.fade_in = 0.0;
+        let mut desktop = Headless::new(SCREEN);
+        let mut cursor = start(Sabotage::Lag, &config, &desktop);
+        let center = desktop.mouse_pos();
+
+        let moved = Vector2::new(100.0, 40.0);
+        let lagging = nudge(&mut cursor, &mut desktop, moved);
+        assert!(lagging.x > center.x && lagging.x < center.x + moved.x, "{lagging:?}");
+
+        cursor.stop(&desktop);
+        assert_eq!(desktop.mouse_pos(), center + moved);
+        assert!(!held());
+    }
+}
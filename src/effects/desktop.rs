@@ -0,0 +1,161 @@
+use std::rc::Rc;
+use std::time::Duration;
+use rand::prelude::*;
+use raylib::prelude::*;
+
+use crate::{
+    canvas::Canvas,
+    config::EffectConfig,
+    effects::{Easing, capture::ScreenCapture, fade, image},
+    loss_handler::{Effect, EffectContext},
+    platform::Screenshot,
+    util::Timer,
+};
+
+// the picture is remade only when progress moved by a step, redoing a whole screen every frame is too slow
+const STEPS: f32 = 20.0;
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Transform {
+    // columns run down the screen
+    Melt,
+    // ever bigger blocks
+    Pixelate,
+    // upside down
+    Flip,
+    // inverted colors
+    Negative,
+    // pieces swap places
+    Jigsaw,
+}
+
+// a fake desktop: a screenshot changed on the cpu and shown over the real one
+pub struct Desktop {
+    transform: Transform,
+    effect_size: Rectangle,
+    duration: Timer,
+    // duration at full severity
+    max_duration: Duration,
+    // shows wherever the changed picture is transparent
+    backdrop: Color,
+    alpha: f32,
+    fade_in: f32,
+    fade_out: f32,
+    easing: Easing,
+    capture: ScreenCapture,
+    // the changed picture and the progress it was made for
+    shown: Option<Rc<Screenshot>>,
+    shown_at: f32,
+    strength: f32,
+    // melt speed of every column
+    drips: Vec<f32>,
+    // jigsaw grid and where every piece goes
+    columns: i32,
+    rows: i32,
+    order: Vec<usize>,
+}
+
+impl Desktop {
+    pub fn new(transform: Transform, screen_size: (i32, i32), config: &EffectConfig) -> Desktop {
+        let duration = Duration::from_secs_f32(config.duration);
+        Desktop {
+            transform,
+            effect_size: rrect(0, 0, screen_size.0, screen_size.1),
+            duration: Timer::new(duration),
+            max_duration: duration,
+            backdrop: Color::new(config.color[0], config.color[1], config.color[2], 255),
+            alpha: config.alpha,
+            fade_in: config.fade_in,
+            fade_out: config.fade_out,
+            easing: config.easing,
+            capture: ScreenCapture::new(),
+            shown: None,
+            shown_at: 0.0,
+            strength: 1.0,
+            drips: vec![],
+            columns: 1,
+            rows: 1,
+            order: vec![],
+        }
+    }
+
+    fn render(&self, screen: &Screenshot, progress: f32) -> Screenshot {
+        match self.transform {
+            Transform::Melt => image::melt(screen, &self.drips, progress),
+            Transform::Pixelate => {
+                let block = 1.0 + (8.0 + 40.0 * self.strength) * progress;
+                image::pixelate(screen, block as i32)
+            }
+            Transform::Flip if progress >= 0.5 => image::flip(screen),
+            Transform::Flip => screen.clone(),
+            Transform::Negative => image::negative(screen, progress),
+            Transform::Jigsaw => image::jigsaw(screen, self.columns, self.rows, &self.order, progress),
+        }
+    }
+
+    pub fn draw_frame(&mut self, d: &mut dyn Canvas, delta_time: f32) {
+        self.duration.update(delta_time);
+        let progress = fade(
+            self.duration.elapsed().as_secs_f32(),
+            self.duration.duration().as_secs_f32(),
+            self.fade_in,
+            self.fade_out,
+            self.easing,
+        );
+        let progress = (progress * STEPS).round() / STEPS;
+
+        if let Some(screen) = self.capture.get()
+            && (self.shown.is_none() || progress != self.shown_at)
+        {
+            self.shown = Some(Rc::new(self.render(screen, progress)));
+            self.shown_at = progress;
+        }
+        if let Some(shown) = &self.shown {
+            let alpha = (self.alpha * 255.0) as u8;
+            d.draw_rectangle(
+                0,
+                0,
+                self.effect_size.width as i32,
+                self.effect_size.height as i32,
+                Color { a: alpha, ..self.backdrop },
+            );
+            d.draw_image(shown, 0.0, 0.0, 0.0, Color { a: alpha, ..Color::WHITE });
+        }
+
+        let text = format!("{:?}", self.duration.time_left());
+        let x = (self.effect_size.x * 2.0 + self.effect_size.width) as i32 / 2 - d.measure_text(&text, 50) / 2;
+        d.draw_text(&text, x + 2, self.effect_size.y as i32 + 10, 50, Color::BLACK);
+    }
+}
+
+impl Effect for Desktop {
+    fn on_start(&mut self, ctx: &mut EffectContext) {
+        let severity = ctx.severity.clamp(0.0, 1.0);
+        self.duration.set_duration(self.max_duration.mul_f32(0.4 + 0.6 * severity));
+        self.strength = 0.4 + 0.6 * severity;
+        self.shown = None;
+        self.capture.take(ctx);
+        let (width, height) = (ctx.window.width as i32, ctx.window.height as i32);
+        self.drips = image::drips(width, ctx.rng);
+        // more and smaller pieces after a worse loss
+        self.columns = 3 + (5.0 * severity) as i32;
+        self.rows = (self.columns * height / width.max(1)).max(2);
+        self.order = (0..(self.columns * self.rows) as usize).collect();
+        self.order.shuffle(ctx.rng);
+    }
+
+    fn draw(&mut self, d: &mut dyn Canvas, delta_time: f32) {
+        self.draw_frame(d, delta_time);
+    }
+
+    fn is_finished(&mut self) -> bool {
+        if self.duration.is_finished() {
+            self.duration.reset();
+            self.capture.clear();
+            self.shown = None;
+            true
+        } else {
+            false
+        }
+    }
+}
@@ -0,0 +1,245 @@
+// picture transforms done on the cpu, so they need no shaders and give the same
+// pixels everywhere. every function leaves its input alone and returns a new picture
+use rand::prelude::*;
+
+use crate::platform::Screenshot;
+
+fn index(width: i32, x: i32, y: i32) -> usize {
+    ((y * width + x) * 4) as usize
+}
+
+// turned by 180 degrees
+pub fn flip(src: &Screenshot) -> Screenshot {
+    let mut pixels = Vec::with_capacity(src.pixels.len());
+    for pixel in src.pixels.chunks_exact(4).rev() {
+        pixels.extend_from_slice(pixel);
+    }
+    Screenshot::new(src.width, src.height, pixels)
+}
+
+// colors mixed amount of the way towards their opposite, 1.0 is a full negative
+pub fn negative(src: &Screenshot, amount: f32) -> Screenshot {
+    let amount = amount.clamp(0.0, 1.0);
+    let mix = |c: u8| (c as f32 + (255.0 - 2.0 * c as f32) * amount).round() as u8;
+    let mut pixels = Vec::with_capacity(src.pixels.len());
+    for pixel in src.pixels.chunks_exact(4) {
+        pixels.extend([mix(pixel[0]), mix(pixel[1]), mix(pixel[2]), pixel[3]]);
+    }
+    Screenshot::new(src.width, src.height, pixels)
+}
+
+// squares of block by block pixels filled with their average color
+pub fn pixelate(src: &Screenshot, block: i32) -> Screenshot {
+    let mut pixels = src.pixels.clone();
+    let block = block.max(1);
+    if block > 1 {
+        for top in (0..src.height).step_by(block as usize) {
+            for left in (0..src.width).step_by(block as usize) {
+                let (right, bottom) = ((left + block).min(src.width), (top + block).min(src.height));
+                let mut sum = [0u32; 4];
+                for y in top..bottom {
+                    for x in left..right {
+                        let i = index(src.width, x, y);
+                        for (total, channel) in sum.iter_mut().zip(&src.pixels[i..i + 4]) {
+                            *total += *channel as u32;
+                        }
+                    }
+                }
+                let count = ((right - left) * (bottom - top)) as u32;
+                let average = sum.map(|total| (total / count) as u8);
+                for y in top..bottom {
+                    for x in left..right {
+                        let i = index(src.width, x, y);
+                        pixels[i..i + 4].copy_from_slice(&average);
+                    }
+                }
+            }
+        }
+    }
+    Screenshot::new(src.width, src.height, pixels)
+}
+
+// how fast each column melts, neighbors are alike so it runs down in drips
+pub fn drips(width: i32, rng: &mut impl Rng) -> Vec<f32> {
+    let mut speed: f32 = rng.random_range(0.2..1.0);
+    (0..width.max(1))
+        .map(|_| {
+            speed = (speed + rng.random_range(-0.03..0.03)).clamp(0.1, 1.0);
+            // now and then a drip runs much further than the rest
+            if rng.random_bool(0.002) { 1.0 } else { speed }
+        })
+        .collect()
+}
+
+// every column slides down by its drip speed times progress of the height, what it
+// leaves behind at the top is transparent
+pub fn melt(src: &Screenshot, drips: &[f32], progress: f32) -> Screenshot {
+    let mut pixels = vec![0u8; src.pixels.len()];
+    if drips.is_empty() {
+        return Screenshot::new(src.width, src.height, pixels);
+    }
+    for x in 0..src.width {
+        let speed = drips[x as usize % drips.len()];
+        let shift = (speed * progress.clamp(0.0, 1.0) * src.height as f32) as i32;
+        for y in shift..src.height {
+            let (from, to) = (index(src.width, x, y - shift), index(src.width, x, y));
+            pixels[to..to + 4].copy_from_slice(&src.pixels[from..from + 4]);
+        }
+    }
+    Screenshot::new(src.width, src.height, pixels)
+}
+
+// the picture cut into columns by rows pieces, piece i is moved progress of the way
+// to the spot of piece order[i]. gaps are transparent
+pub fn jigsaw(src: &Screenshot, columns: i32, rows: i32, order: &[usize], progress: f32) -> Screenshot {
+    let (columns, rows) = (columns.max(1), rows.max(1));
+    let mut pixels = vec![0u8; src.pixels.len()];
+    // top left corner and size of a piece, the last ones take the rounding
+    let piece = |i: usize| {
+        let (column, row) = (i as i32 % columns, i as i32 / columns);
+        let (left, top) = (column * src.width / columns, row * src.height / rows);
+        let right = (column + 1) * src.width / columns;
+        let bottom = (row + 1) * src.height / rows;
+        (left, top, right - left, bottom - top)
+    };
+    let progress = progress.clamp(0.0, 1.0);
+    for i in 0..(columns * rows) as usize {
+        let (left, top, width, height) = piece(i);
+        let (slot_left, slot_top, _, _) = piece(order.get(i).copied().unwrap_or(i));
+        let x = left + ((slot_left - left) as f32 * progress) as i32;
+        let y = top + ((slot_top - top) as f32 * progress) as i32;
+        for row in 0..height {
+            let to_y = y + row;
+            if to_y < 0 || to_y >= src.height {
+                continue;
+            }
+            for column in 0..width {
+                let to_x = x + column;
+                if to_x < 0 || to_x >= src.width {
+                    continue;
+                }
+                let (from, to) = (index(src.width, left + column, top + row), index(src.width, to_x, to_y));
+                pixels[to..to + 4].copy_from_slice(&src.pixels[from..from + 4]);
+            }
+        }
+    }
+    Screenshot::new(src.width, src.height, pixels)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::rngs::StdRng;
+
+    // one letter per pixel, so fixtures and goldens read like the pictures they are
+    const PALETTE: [(char, [u8; 4]); 10] = [
+        ('.', [0, 0, 0, 0]),
+        ('r', [255, 0, 0, 255]),
+        ('g', [0, 255, 0, 255]),
+        ('b', [0, 0, 255, 255]),
+        ('w', [255, 255, 255, 255]),
+        ('c', [0, 255, 255, 255]),
+        ('m', [255, 0, 255, 255]),
+        ('y', [255, 255, 0, 255]),
+        ('k', [0, 0, 0, 255]),
+        (',', [255, 255, 255, 0]),
+    ];
+
+    // the fixture every transform is checked on, with one transparent pixel
+    const FIXTURE: [&str; 4] = ["rrgg", "rrgg", "bbww", "bbw."];
+
+    fn picture(rows: &[&str]) -> Screenshot {
+        let mut pixels = vec![];
+        for row in rows {
+            for letter in row.chars() {
+                let (_, color) = PALETTE.iter().find(|(l, _)| *l == letter).unwrap();
+                pixels.extend(color);
+            }
+        }
+        Screenshot::new(rows[0].len() as i32, rows.len() as i32, pixels)
+    }
+
+    fn letters(image: &Screenshot) -> Vec<String> {
+        image
+            .pixels
+            .chunks_exact(4)
+            .map(|pixel| match PALETTE.iter().find(|(_, color)| color == pixel) {
+                Some((letter, _)) => *letter,
+                None => panic!("{pixel:?} is not in the palette"),
+            })
+            .collect::<Vec<char>>()
+            .chunks(image.width as usize)
+            .map(|row| row.iter().collect())
+            .collect()
+    }
+
+    #[test]
+    fn flip_golden() {
+        assert_eq!(letters(&flip(&picture(&FIXTURE))), [".wbb", "wwbb", "ggrr", "ggrr"]);
+    }
+
+    #[test]
+    fn negative_golden() {
+        let src = picture(&FIXTURE);
+        assert_eq!(letters(&negative(&src, 0.0)), FIXTURE);
+        assert_eq!(letters(&negative(&src, 1.0)), ["ccmm", "ccmm", "yykk", "yyk,"]);
+        // halfway every channel meets in the middle, alpha stays
+        let half = negative(&src, 0.5);
+        for (pixel, alpha) in half.pixels.chunks_exact(4).zip(src.pixels.chunks_exact(4).map(|p| p[3])) {
+            assert_eq!(pixel, [128, 128, 128, alpha]);
+        }
+    }
+
+    #[test]
+    fn pixelate_golden() {
+        let src = picture(&FIXTURE);
+        assert_eq!(letters(&pixelate(&src, 1)), FIXTURE);
+        let blocks = pixelate(&src, 2);
+        assert_eq!(blocks.pixels[..32], picture(&["rrgg", "rrgg"]).pixels);
+        assert_eq!(blocks.pixels[32..40], [0, 0, 255, 255, 0, 0, 255, 255]);
+        assert_eq!(blocks.pixels[40..48], [191, 191, 191, 191, 191, 191, 191, 191]);
+        // blocks at the edges are cut off and averaged over what is left of them
+        let uneven = pixelate(&src, 3);
+        let pixel = |x: i32, y: i32| &uneven.pixels[index(4, x, y)..index(4, x, y) + 4];
+        assert_eq!(pixel(0, 0), [141, 85, 85, 255]);
+        assert_eq!(pixel(2, 2), [141, 85, 85, 255]);
+        assert_eq!(pixel(3, 0), [85, 255, 85, 255]);
+        assert_eq!(pixel(0, 3), [85, 85, 255, 255]);
+        assert_eq!(pixel(3, 3), [0, 0, 0, 0]);
+    }
+
+    #[test]
+    fn melt_golden() {
+        let src = picture(&FIXTURE);
+        let drips = [0.0, 0.25, 0.5, 1.0];
+        assert_eq!(letters(&melt(&src, &drips, 0.0)), FIXTURE);
+        assert_eq!(letters(&melt(&src, &drips, 0.5)), ["rr..", "rrg.", "bbgg", "bbwg"]);
+        assert_eq!(letters(&melt(&src, &drips, 1.0)), ["r...", "rr..", "brg.", "bbg."]);
+    }
+
+    #[test]
+    fn melt_seeded_golden() {
+        let drips = drips(4, &mut StdRng::seed_from_u64(1));
+        // neighbors drift by a few hundredths at most
+        let rounded: Vec<f32> = drips.iter().map(|speed| (speed * 1000.0).round()).collect();
+        assert_eq!(rounded, [888.0, 870.0, 851.0, 852.0]);
+        assert_eq!(letters(&melt(&picture(&FIXTURE), &drips, 1.0)), ["....", "....", "....", "rrgg"]);
+    }
+
+    #[test]
+    fn jigsaw_golden() {
+        let src = picture(&FIXTURE);
+        let order = [3, 2, 1, 0];
+        assert_eq!(letters(&jigsaw(&src, 2, 2, &order, 0.0)), FIXTURE);
+        // halfway every piece is on the middle, the last one drawn on top
+        assert_eq!(letters(&jigsaw(&src, 2, 2, &order, 0.5)), ["....", ".ww.", ".w..", "...."]);
+        assert_eq!(letters(&jigsaw(&src, 2, 2, &order, 1.0)), ["wwbb", "w.bb", "ggrr", "ggrr"]);
+    }
+
+    #[test]
+    fn jigsaw_seeded_golden() {
+        let mut order: Vec<usize> = (0..8).collect();
+        order.shuffle(&mut StdRng::seed_from_u64(1));
+        assert_eq!(letters(&jigsaw(&picture(&FIXTURE), 2, 4, &order, 1.0)), ["rrbb", "wwgg", "bbrr", "w.gg"]);
+    }
+}
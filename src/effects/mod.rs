@@ -1 +1,48 @@
-pub mod screen_flash;
\ No newline at end of file
+pub mod capture;
+pub mod cursor;
+pub mod desktop;
+pub mod image;
+pub mod screen_flash;
+pub mod shake;
+
+// how an effect moves between invisible and full strength
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Easing {
+    Linear,
+    // slow start
+    EaseIn,
+    // slow end
+    EaseOut,
+    // slow start and end
+    EaseInOut,
+}
+
+impl Easing {
+    pub fn from_name(name: &str) -> Option<Easing> {
+        match name {
+            "linear" => Some(Easing::Linear),
+            "ease_in" => Some(Easing::EaseIn),
+            "ease_out" => Some(Easing::EaseOut),
+            "ease_in_out" => Some(Easing::EaseInOut),
+            _ => None,
+        }
+    }
+
+    // maps progress from 0.0 to 1.0 onto the curve
+    pub fn apply(self, t: f32) -> f32 {
+        let t = t.clamp(0.0, 1.0);
+        match self {
+            Easing::Linear => t,
+            Easing::EaseIn => t * t,
+            Easing::EaseOut => 1.0 - (1.0 - t) * (1.0 - t),
+            Easing::EaseInOut => t * t * (3.0 - 2.0 * t),
+        }
+    }
+}
+
+// strength of an effect elapsed seconds into a run of length seconds, fading in and out at the ends
+pub fn fade(elapsed: f32, length: f32, fade_in: f32, fade_out: f32, easing: Easing) -> f32 {
+    let rising = if fade_in > 0.0 { elapsed / fade_in } else { 1.0 };
+    let falling = if fade_out > 0.0 { (length - elapsed) / fade_out } else { 1.0 };
+    easing.apply(rising.min(falling))
+}
@@ -1,47 +1,110 @@
-use std::{time::Duration, ffi::CString};
-use raylib::{prelude::*, ffi::MeasureText};
+use std::time::Duration;
+use raylib::prelude::*;
 
 
-use crate::{loss_handler::Effect, util::Timer};
+use crate::{
+    canvas::Canvas,
+    config::EffectConfig,
+    effects::{Easing, fade},
+    loss_handler::{Effect, EffectContext},
+    util::Timer,
+};
 
 pub struct Flash {
     effect_size: Rectangle,
     duration: Timer,
+    // duration at full severity
+    max_duration: Duration,
+    color: Color,
+    // opacity at full severity
+    max_alpha: f32,
+    alpha: f32,
+    fade_in: f32,
+    fade_out: f32,
+    easing: Easing,
+    // share of the opacity a narrow loss still gets
+    narrow_alpha: f32,
+    text_color: Color,
+    gentle: bool,
 }
 
 impl Flash {
-    pub fn new(screen_size: (i32, i32), duration: Duration) -> Flash {
+    pub fn new(screen_size: (i32, i32), config: &EffectConfig) -> Flash {
+        Flash::with_style(screen_size, config, 0.63, Color::BLACK, false)
+    }
+
+    // the gentle stand in for the other effects in safe mode: a slow, partial darkening with
+    // a light countdown, a narrow loss dims a little over half as much
+    pub fn dim(screen_size: (i32, i32), config: &EffectConfig) -> Flash {
+        Flash::with_style(screen_size, config, 0.57, Color::LIGHTGRAY, true)
+    }
+
+    fn with_style(
+        screen_size: (i32, i32),
+        config: &EffectConfig,
+        narrow_alpha: f32,
+        text_color: Color,
+        gentle: bool,
+    ) -> Flash {
+        let duration = Duration::from_secs_f32(config.duration);
         Flash {
             effect_size: rrect(0, 0, screen_size.0, screen_size.1),
             duration: Timer::new(duration),
+            max_duration: duration,
+            color: Color::new(config.color[0], config.color[1], config.color[2], 255),
+            max_alpha: config.alpha,
+            alpha: config.alpha,
+            fade_in: config.fade_in,
+            fade_out: config.fade_out,
+            easing: config.easing,
+            narrow_alpha,
+            text_color,
+            gentle,
         }
     }
 
-    pub fn draw_frame(&mut self, d: &mut RaylibDrawHandle, delta_time: f32) {
+    pub fn draw_frame(&mut self, d: &mut dyn Canvas, delta_time: f32) {
         self.duration.update(delta_time);
-        d.draw_rectangle(0 as i32, 0 as i32, self.effect_size.width as i32, self.effect_size.height as i32, Color::DARKRED);
-
-        unsafe {
-            let time_left = self.duration.time_left();
-            let text = CString::new(format!("{:?}", time_left as i32)).unwrap();
-            d.draw_text(
-                &format!("{}", text.to_str().unwrap()),
-                (self.effect_size.x * 2.0 + self.effect_size.width) as i32 / 2
-                    - (MeasureText(text.as_ptr() as *const i8, 50) as i32 / 2)
-                    + 2,
-                self.effect_size.y as i32 + 10,
-                50,
-                Color::BLACK,
-            );
-        }
+        let strength = fade(
+            self.duration.elapsed().as_secs_f32(),
+            self.duration.duration().as_secs_f32(),
+            self.fade_in,
+            self.fade_out,
+            self.easing,
+        );
+        let alpha = (self.alpha * strength * 255.0) as u8;
+        d.draw_rectangle(0, 0, self.effect_size.width as i32, self.effect_size.height as i32, Color { a: alpha, ..self.color });
+
+        let time_left = self.duration.time_left();
+        let text = format!("{:?}", time_left);
+        let x = (self.effect_size.x * 2.0 + self.effect_size.width) as i32 / 2 - d.measure_text(&text, 50) / 2;
+        d.draw_text(
+            &text,
+            x + 2,
+            self.effect_size.y as i32 + 10,
+            50,
+            Color { a: (255.0 * strength) as u8, ..self.text_color },
+        );
     }
 }
 
 impl Effect for Flash {
-    fn draw(&mut self, d: &mut RaylibDrawHandle, delta_time: f32) {
+    fn on_start(&mut self, ctx: &mut EffectContext) {
+        let severity = ctx.severity.clamp(0.0, 1.0);
+        // a narrow loss still flashes for 40% of the time at a lighter shade
+        let scale = 0.4 + 0.6 * severity;
+        self.duration.set_duration(self.max_duration.mul_f32(scale));
+        self.alpha = self.max_alpha * (self.narrow_alpha + (1.0 - self.narrow_alpha) * severity);
+    }
+
+    fn draw(&mut self, d: &mut dyn Canvas, delta_time: f32) {
         self.draw_frame(d, delta_time);
     }
 
+    fn is_gentle(&self) -> bool {
+        self.gentle
+    }
+
     fn is_finished(&mut self) -> bool {
         if self.duration.is_finished() {
             self.duration.reset();
@@ -50,4 +113,4 @@ impl Effect for Flash {
             false
         }
     }
-}
\ No newline at end of file
+}
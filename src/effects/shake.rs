@@ -0,0 +1,192 @@
+use std::time::Duration;
+use rand::prelude::*;
+use rand::rngs::StdRng;
+use raylib::prelude::*;
+
+use crate::{
+    canvas::Canvas,
+    config::EffectConfig,
+    effects::{Easing, capture::ScreenCapture, fade},
+    loss_handler::{Effect, EffectContext},
+    util::Timer,
+};
+
+// pixels the picture moves at most after the worst loss
+const MAX_OFFSET: f32 = 40.0;
+// degrees the picture turns at most after the worst loss
+const MAX_ROTATION: f32 = 3.0;
+// seconds between new random positions, jumping every frame would look like flicker
+const JOLT_INTERVAL: f32 = 0.05;
+
+// shakes a screenshot of the desktop so the whole screen seems to tremble, calming down over time
+pub struct Shake {
+    effect_size: Rectangle,
+    duration: Timer,
+    // duration at full severity
+    max_duration: Duration,
+    // drawn behind the picture so the edges it leaves uncovered do not show the real desktop
+    backdrop: Color,
+    alpha: f32,
+    fade_in: f32,
+    fade_out: f32,
+    easing: Easing,
+    capture: ScreenCapture,
+    rng: StdRng,
+    // how hard this loss shakes, 0.0 to 1.0
+    strength: f32,
+    // current jolt as offset and rotation at full strength, and the time until the next one
+    offset: Vector2,
+    rotation: f32,
+    next_jolt: f32,
+}
+
+impl Shake {
+    pub fn new(screen_size: (i32, i32), config: &EffectConfig) -> Shake {
+        let duration = Duration::from_secs_f32(config.duration);
+        Shake {
+            effect_size: rrect(0, 0, screen_size.0, screen_size.1),
+            duration: Timer::new(duration),
+            max_duration: duration,
+            backdrop: Color::new(config.color[0], config.color[1], config.color[2], 255),
+            alpha: config.alpha,
+            fade_in: config.fade_in,
+            fade_out: config.fade_out,
+            easing: config.easing,
+            capture: ScreenCapture::new(),
+            rng: StdRng::seed_from_u64(0),
+            strength: 1.0,
+            offset: Vector2::zero(),
+            rotation: 0.0,
+            next_jolt: 0.0,
+        }
+    }
+
+    fn jolt(&mut self) {
+        self.offset = Vector2::new(self.rng.random_range(-1.0..1.0), self.rng.random_range(-1.0..1.0));
+        self.rotation = self.rng.random_range(-1.0..1.0);
+        self.next_jolt = JOLT_INTERVAL;
+    }
+
+    pub fn draw_frame(&mut self, d: &mut dyn Canvas, delta_time: f32) {
+        self.duration.update(delta_time);
+        self.next_jolt -= delta_time;
+        if self.next_jolt <= 0.0 {
+            self.jolt();
+        }
+        // fade_out is how the shaking dies down
+        let calm = fade(
+            self.duration.elapsed().as_secs_f32(),
+            self.duration.duration().as_secs_f32(),
+            self.fade_in,
+            self.fade_out,
+            self.easing,
+        );
+        let amount = self.strength * calm;
+
+        if let Some(screen) = self.capture.get() {
+            let alpha = (self.alpha * 255.0) as u8;
+            d.draw_rectangle(
+                0,
+                0,
+                self.effect_size.width as i32,
+                self.effect_size.height as i32,
+                Color { a: alpha, ..self.backdrop },
+            );
+            d.draw_image(
+                screen,
+                self.offset.x * MAX_OFFSET * amount,
+                self.offset.y * MAX_OFFSET * amount,
+                self.rotation * MAX_ROTATION * amount,
+                Color { a: alpha, ..Color::WHITE },
+            );
+        }
+
+        let text = format!("{:?}", self.duration.time_left());
+        let x = (self.effect_size.x * 2.0 + self.effect_size.width) as i32 / 2 - d.measure_text(&text, 50) / 2;
+        d.draw_text(&text, x + 2, self.effect_size.y as i32 + 10, 50, Color::BLACK);
+    }
+}
+
+impl Effect for Shake {
+    fn on_start(&mut self, ctx: &mut EffectContext) {
+        let severity = ctx.severity.clamp(0.0, 1.0);
+        self.duration.set_duration(self.max_duration.mul_f32(0.4 + 0.6 * severity));
+        self.strength = 0.4 + 0.6 * severity;
+        self.rng = StdRng::from_rng(ctx.rng);
+        self.next_jolt = 0.0;
+        self.capture.take(ctx);
+    }
+
+    fn draw(&mut self, d: &mut dyn Canvas, delta_time: f32) {
+        self.draw_frame(d, delta_time);
+    }
+
+    fn is_finished(&mut self) -> bool {
+        if self.duration.is_finished() {
+            self.duration.reset();
+            self.capture.clear();
+            true
+        } else {
+            false
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::canvas::{DrawCommand, Recorder};
+    use crate::config::EffectsConfig;
+    use crate::platform::{Screenshot, headless::Headless};
+
+    #[test]
+    fn shakes_the_captured_screen_by_seeded_offsets() {
+        let mut desktop = Headless::new((4, 2));
+        let screen = Screenshot::new(4, 2, (0..32).collect());
+        desktop.set_screen(screen.clone());
+        let mut rng = StdRng::seed_from_u64(7);
+        let mut ctx = EffectContext {
+            severity: 1.0,
+            rng: &mut rng,
+            desktop: &desktop,
+            window: Rectangle::new(0.0, 0.0, 4.0, 2.0),
+        };
+        // without a fade out every jolt is drawn at full strength
+        let mut config = EffectsConfig::default().shake;
+        config.fade_out = 0.0;
+        let mut shake = Shake::new((4, 2), &config);
+        shake.on_start(&mut ctx);
+
+        // the shake draws from its own stream split off the one it was given
+        let mut expected = StdRng::from_rng(&mut StdRng::seed_from_u64(7));
+        let mut recorder = Recorder::new();
+        for _ in 0..5 {
+            // a new jolt every frame at this frame time
+            shake.draw(&mut recorder, JOLT_INTERVAL);
+            let (x, y, rotation) = (
+                expected.random_range(-1.0..1.0) * MAX_OFFSET,
+                expected.random_range(-1.0..1.0) * MAX_OFFSET,
+                expected.random_range(-1.0..1.0) * MAX_ROTATION,
+            );
+            let images: Vec<_> = recorder
+                .take()
+                .into_iter()
+                .filter_map(|command| match command {
+                    DrawCommand::Image {
+                        image,
+                        x,
+                        y,
+                        rotation,
+                        ..
+                    } => Some((image, x, y, rotation)),
+                    _ => None,
+                })
+                .collect();
+            assert_eq!(images.len(), 1);
+            let (image, drawn_x, drawn_y, drawn_rotation) = &images[0];
+            assert_eq!(**image, screen);
+            assert_eq!((*drawn_x, *drawn_y, *drawn_rotation), (x, y, rotation));
+            assert!(x.abs() <= MAX_OFFSET && y.abs() <= MAX_OFFSET);
+        }
+    }
+}
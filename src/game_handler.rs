@@ -1,158 +1,523 @@
+use crate::canvas::Canvas;
+use crate::config::{ActivityConfig, Config, Watcher};
+use crate::cooldown::Cooldown;
+use crate::difficulty::Ratings;
 use crate::games::{avoider::Avoider, circles::Circles, pong::Pong};
-use rand::prelude::*;
+use crate::history::{self, History, Round};
+use crate::input::InputState;
+use crate::overlay::Overlay;
+use crate::schedule::Schedule;
+use crate::selection::Selector;
+use chrono::{DateTime, Local};
+use rand::rngs::StdRng;
 use raylib::prelude::*;
-use std::time::{Duration, SystemTime};
-use std::{fs::File, io::Read};
-use yaml_rust2::YamlLoader;
-
-fn draw_title(d: &mut RaylibDrawHandle, title: &str) {
-    d.draw_text("current game:", 9, 9, 30, Color::WHITE);
-    d.draw_text("current game:", 11, 11, 30, Color::WHITE);
-    d.draw_text("current game:", 10, 10, 30, Color::BLACK);
-    d.draw_text(title, 9, 39, 50, Color::WHITE);
-    d.draw_text(title, 11, 41, 50, Color::WHITE);
-    d.draw_text(title, 10, 40, 50, Color::BLACK);
+use std::path::PathBuf;
+use std::time::Duration;
+
+fn draw_title(d: &mut dyn Canvas, title: &str, area: Rectangle) {
+    let (x, y) = (area.x as i32, area.y as i32);
+    d.draw_text("current game:", x + 9, y + 9, 30, Color::WHITE);
+    d.draw_text("current game:", x + 11, y + 11, 30, Color::WHITE);
+    d.draw_text("current game:", x + 10, y + 10, 30, Color::BLACK);
+    d.draw_text(title, x + 9, y + 39, 50, Color::WHITE);
+    d.draw_text(title, x + 11, y + 41, 50, Color::WHITE);
+    d.draw_text(title, x + 10, y + 40, 50, Color::BLACK);
+}
+
+// enabled games and the selector that picks between them
+fn build_games(config: &Config) -> (Vec<Box<dyn Game>>, Selector) {
+    let mut games: Vec<Box<dyn Game>> = vec![];
+    let mut weights: Vec<(String, f32)> = vec![];
+    if config.pong.enabled {
+        games.push(Box::new(Pong::new(&config.pong)));
+        weights.push(("Pong".to_string(), config.pong.weight));
+    }
+    if config.circles.enabled {
+        games.push(Box::new(Circles::new(&config.circles)));
+        weights.push(("Circles".to_string(), config.circles.weight));
+    }
+    if config.avoider.enabled {
+        games.push(Box::new(Avoider::new(&config.avoider)));
+        weights.push(("Avoider".to_string(), config.avoider.weight));
+    }
+    let selector = Selector::new(config.settings.selection, &weights, &config.settings.playlist);
+    (games, selector)
+}
+
+fn cooldown_range(config: &Config) -> Cooldown {
+    Cooldown::new(
+        config.settings.min_cooldown,
+        config.settings.max_cooldown,
+        config.settings.cooldown_distribution,
+    )
+}
+
+fn draw_paused(d: &mut dyn Canvas, area: Rectangle) {
+    let (x, y) = (area.x as i32, area.y as i32);
+    d.draw_text("paused, move the mouse to continue", x + 9, y + 99, 30, Color::WHITE);
+    d.draw_text("paused, move the mouse to continue", x + 11, y + 101, 30, Color::WHITE);
+    d.draw_text("paused, move the mouse to continue", x + 10, y + 100, 30, Color::BLACK);
 }
 
 pub struct GameHandler {
-    now: SystemTime,
+    // time of the current frame, set from outside so a recording can replay it
+    clock: DateTime<Local>,
+    // when the last game ended, the cooldown counts from here
+    last_game: DateTime<Local>,
+    // time until the next game, drawn from cooldown_range
     cooldown: Duration,
+    cooldown_range: Cooldown,
     game_list: Vec<Box<dyn Game>>,
+    selector: Selector,
     selected: Option<usize>,
+    overlay: Overlay,
+    area: Rectangle,
+    watcher: Option<Watcher>,
+    // reloaded settings waiting for the running game to end
+    pending: Option<Config>,
+    playing: bool,
+    schedule: Schedule,
+    // whether the schedule allowed games last time ready() looked
+    schedule_open: bool,
+    activity: ActivityConfig,
+    // time since the last input on the desktop
+    idle: Duration,
+    // idle long enough that no game may start
+    away: bool,
+    // idle long enough that the running game is frozen
+    paused: bool,
+    // time the running game has been played, without pauses
+    round_time: Duration,
+    // clock time the running game started at
+    round_start: DateTime<Local>,
+    // every random choice comes from here so a seed replays the same session
+    rng: StdRng,
+    // fixed logic step in seconds, drawing interpolates between steps
+    step: f32,
+    max_steps: u32,
+    // frame time not simulated yet, always less than a step between frames
+    accumulator: f32,
+    // input of frames that ran no step, its clicks are handed to the next step
+    missed: Option<InputState>,
+    // settings the current game list was built from
+    settings: Config,
+    history: Option<History>,
+    // round time of the first click or key press in the running game
+    reaction: Option<Duration>,
+    ratings: Ratings,
+    // whether ratings change the games, otherwise every game plays at level 0
+    adaptive: bool,
 }
 
 impl GameHandler {
-    pub fn new(screen_size: (i32, i32)) -> Result<GameHandler, Box<dyn std::error::Error>> {
-        let exe_path = std::env::current_exe()?;
-        let exe_dir = exe_path
-            .parent()
-            .ok_or("Could not get executable directory")?;
-        let settings_path = exe_dir.join("settings.yaml");
-
-        let mut contents = String::new();
-        // Check if file exists
-        if !settings_path.exists() {
-            println!("no settings file. creating one");
-                        let settings_content = 
-r#"Settings:
-    - 120.0    # Time between games (seconds)
-            
-Pong:
-  - true    # Enable/disable game
-  - 300.0   # Ball speed
-  - 200.0   # Paddle speed
-  - 200.0   # AI paddle speed
-
-Circles:
-  - true    # Enable/disable game
-  - 4       # Minimum circles
-  - 10      # Maximum circles
-  - 5.0     # Time limit (seconds)
-
-Avoider:
-  - true    # Enable/disable game
-  - 15.0    # Time limit (seconds)
-  - 0.6     # Object spawn interval (seconds)
-"#;
-            std::fs::write(&settings_path, settings_content)?;
-            contents.push_str(settings_content);
-        } else {
-            println!("settings file found");
-            let mut file = File::open(&settings_path)?;
-            file.read_to_string(&mut contents)?;
-        }
+    pub fn new(
+        config: &Config,
+        overlay: Overlay,
+        mut rng: StdRng,
+        now: DateTime<Local>,
+    ) -> Result<GameHandler, Box<dyn std::error::Error>> {
+        let area = overlay.game_area(&mut rng);
 
-        let temp_settings = YamlLoader::load_from_str(&contents)?;
-        if temp_settings.is_empty() {
-            return Err("YAML file is empty".into());
+        let (games, selector) = build_games(config);
+        if games.is_empty() {
+            return Err("every game is disabled in settings.yaml".into());
         }
 
-        let settings = &temp_settings[0];
-
-        // Validate required settings exist
-        let game_settings = settings["Settings"]
-            .as_vec()
-            .ok_or("Game Settings not found or invalid")?;
+        let cooldown_range = cooldown_range(config);
+        Ok(GameHandler {
+            clock: now,
+            last_game: now,
+            cooldown: cooldown_range.next(&mut rng),
+            cooldown_range,
+            game_list: games,
+            selector,
+            selected: None,
+            overlay,
+            area,
+            watcher: None,
+            pending: None,
+            playing: false,
+            schedule: config.schedule.clone(),
+            schedule_open: true,
+            activity: config.activity.clone(),
+            idle: Duration::ZERO,
+            away: false,
+            paused: false,
+            round_time: Duration::ZERO,
+            round_start: now,
+            rng,
+            step: 1.0 / config.settings.tick_rate,
+            max_steps: config.settings.max_catch_up_steps as u32,
+            accumulator: 0.0,
+            missed: None,
+            settings: config.clone(),
+            history: None,
+            reaction: None,
+            ratings: Ratings::new(),
+            adaptive: config.settings.adaptive_difficulty,
+        })
+    }
 
-        let pong_settings = settings["Pong"]
-            .as_vec()
-            .ok_or("Pong settings not found or invalid")?;
+    // append every finished round to this history
+    pub fn keep_history(&mut self, history: History) {
+        self.history = Some(history);
+    }
 
-        let circles_settings = settings["Circles"]
-            .as_vec()
-            .ok_or("Circles settings not found or invalid")?;
+    // difficulty levels to start from, updated after every round
+    pub fn rate_difficulty(&mut self, ratings: Ratings) {
+        self.ratings = ratings;
+    }
 
-        let avoider_settings = settings["Avoider"]
-            .as_vec()
-            .ok_or("Avoider settings not found or invalid")?;
+    // reload the settings whenever this file changes
+    pub fn watch(&mut self, path: PathBuf) {
+        self.watcher = Some(Watcher::new(path));
+    }
 
-        let mut games: Vec<Box<dyn Game>> = vec![];
-        if pong_settings[0].as_bool().unwrap() {
-            games.push(Box::new(Pong::new(screen_size, pong_settings)));
-        }
-        if circles_settings[0].as_bool().unwrap() {
-            games.push(Box::new(Circles::new(screen_size, circles_settings)));
+    // call every frame, picks up changes to the watched settings file and returns them
+    pub fn reload_settings(&mut self) -> Option<Config> {
+        let mut reloaded = None;
+        if let Some(watcher) = &mut self.watcher {
+            match watcher.poll() {
+                None => {}
+                Some(Err(e)) => {
+                    println!("settings reload failed, keeping the previous settings:\n{e}");
+                }
+                Some(Ok(config)) => {
+                    if config.settings.monitor != self.overlay.mode() {
+                        println!("monitor change needs a restart to take effect");
+                    }
+                    // only a changed range draws a new wait, saving other settings keeps the current one
+                    let range = cooldown_range(&config);
+                    if range != self.cooldown_range {
+                        self.cooldown_range = range;
+                        self.cooldown = range.next(&mut self.rng);
+                    }
+                    self.schedule = config.schedule.clone();
+                    self.activity = config.activity.clone();
+                    self.adaptive = config.settings.adaptive_difficulty;
+                    reloaded = Some(config.clone());
+                    self.pending = Some(config);
+                }
+            }
         }
-        if avoider_settings[0].as_bool().unwrap() {
-            games.push(Box::new(Avoider::new(screen_size, avoider_settings)));
+        if !self.playing {
+            self.apply_pending();
         }
+        reloaded
+    }
 
-        Ok(GameHandler {
-            now: SystemTime::now(),
-            cooldown: Duration::from_secs_f32(
-                game_settings[0]
-                    .as_f64()
-                    .ok_or("Invalid Game Settings cooldown")? as f32,
-            ),
-            game_list: games,
-            selected: None,
-        })
+    fn apply_pending(&mut self) {
+        if let Some(config) = self.pending.take() {
+            (self.game_list, self.selector) = build_games(&config);
+            // changing the step under a running game would change how it plays out
+            self.step = 1.0 / config.settings.tick_rate;
+            self.max_steps = config.settings.max_catch_up_steps as u32;
+            self.settings = config;
+            self.select_game();
+            println!("settings reloaded");
+        }
     }
 
     pub fn ready(&mut self) -> bool {
-        if self.now.elapsed().unwrap() > self.cooldown {
-            true
+        // a game that already started is allowed to finish
+        if !self.playing && !self.schedule_allows() {
+            return false;
+        }
+        let waited = (self.clock - self.last_game).to_std().unwrap_or(Duration::ZERO);
+        if waited > self.cooldown {
+            self.playing || self.user_active()
         } else {
             false
         }
     }
 
+    // call every frame with the current time
+    pub fn set_clock(&mut self, now: DateTime<Local>) {
+        self.clock = now;
+    }
+
+    // call every frame with the platform idle time
+    pub fn set_idle(&mut self, idle: Duration) {
+        self.idle = idle;
+    }
+
+    // true if idle for longer than the limit, a limit of 0 never triggers
+    fn idle_longer_than(&self, limit: f32) -> bool {
+        limit > 0.0 && self.idle.as_secs_f32() > limit
+    }
+
+    // like idle_longer_than, but input from before the running game started does not count,
+    // so a game that starts after a quiet spell is not paused on its first frame
+    fn idle_in_round_longer_than(&self, limit: f32) -> bool {
+        let since_start = (self.clock - self.round_start).to_std().unwrap_or(Duration::ZERO);
+        limit > 0.0 && self.idle.min(since_start).as_secs_f32() > limit
+    }
+
+    fn user_active(&mut self) -> bool {
+        let away = self.idle_longer_than(self.activity.start_idle_limit);
+        if away != self.away {
+            self.away = away;
+            if away {
+                println!("user idle, waiting for activity before starting a game");
+            } else {
+                println!("user active again");
+            }
+        }
+        !away
+    }
+
+    fn schedule_allows(&mut self) -> bool {
+        let now = self.clock.naive_local();
+        let open = self.schedule.allows(now);
+        if open != self.schedule_open {
+            self.schedule_open = open;
+            if open {
+                println!("schedule: games may start again");
+            } else {
+                match self.schedule.next_open(now) {
+                    Some(next) => println!("schedule: no games until {}", next.format("%a %H:%M")),
+                    None => println!("schedule: no games in the coming week"),
+                }
+            }
+        }
+        open
+    }
+
     pub fn select_game(&mut self) {
-        let mut rng = rand::rng(); // use correct thread_rng
-        let num = rng.random_range(0..self.game_list.len());
+        let num = self.selector.next(&mut self.rng);
         self.selected = Some(num);
         println!("selected: {num}");
     }
 
-    pub fn do_frame(&mut self, delta_time: f32, mouse_pos: Vector2, d: &mut RaylibDrawHandle) {
+    pub fn do_frame(&mut self, delta_time: f32, input: &InputState, d: &mut dyn Canvas) {
         if let Some(index) = self.selected {
+            if !self.playing {
+                self.playing = true;
+                self.paused = false;
+                self.round_time = Duration::ZERO;
+                self.round_start = self.clock;
+                self.accumulator = 0.0;
+                self.missed = None;
+                self.reaction = None;
+                self.area = self.overlay.game_area(&mut self.rng);
+                let game = &mut self.game_list[index];
+                let level = if self.adaptive { self.ratings.level(game.get_info()) } else { 0.0 };
+                game.set_difficulty(level);
+                game.on_start(&mut GameContext {
+                    area: self.area,
+                    rng: &mut self.rng,
+                });
+            }
+            let paused = self.idle_in_round_longer_than(self.activity.pause_idle_limit);
+            if paused != self.paused {
+                self.paused = paused;
+                if paused {
+                    println!("game paused, user idle");
+                    self.game_list[index].pause();
+                } else {
+                    println!("game resumed");
+                    self.game_list[index].resume();
+                }
+            }
+
             let game = &mut self.game_list[index];
-            game.logic(mouse_pos, delta_time);
-            game.draw(d);
-            draw_title(d, game.get_info());
+            // skipping logic freezes the game timers too
+            if !paused {
+                // games work in window coordinates
+                let mut input = input.clone();
+                input.cursor = self.overlay.to_window(input.cursor);
+                if let Some(missed) = self.missed.take() {
+                    input.carry_edges(&missed);
+                }
+
+                // fixed steps so collisions do not depend on the frame rate
+                self.accumulator += delta_time;
+                let mut steps = 0;
+                while self.accumulator >= self.step && game.is_finished().is_none() {
+                    if steps == self.max_steps {
+                        // too far behind after a hitch, skip ahead instead of spiralling
+                        self.accumulator = 0.0;
+                        break;
+                    }
+                    if self.reaction.is_none() && input.any_pressed() {
+                        self.reaction = Some(self.round_time);
+                    }
+                    game.logic(&input, self.step);
+                    input = input.without_edges();
+                    self.round_time += Duration::from_secs_f32(self.step);
+                    self.accumulator -= self.step;
+                    steps += 1;
+                }
+                if steps == 0 {
+                    self.missed = Some(input);
+                }
+            }
+            let alpha = if paused { 1.0 } else { self.accumulator / self.step };
+            game.draw(d, alpha.clamp(0.0, 1.0));
+            draw_title(d, game.get_info(), self.area);
+            if paused {
+                draw_paused(d, self.area);
+            }
         }
     }
 
-    pub fn finished(&mut self) -> Option<bool> {
+    pub fn finished(&mut self) -> Option<GameOutcome> {
+        if !self.playing {
+            return None;
+        }
         if let Some(index) = self.selected {
             let game = &mut self.game_list[index];
-            if let Some(game_over) = game.is_finished() {
-                self.now = SystemTime::now();
-                self.select_game();
-                return Some(game_over);
+            if let Some(outcome) = game.is_finished() {
+                let name = game.get_info().to_string();
+                println!("{name} finished: {outcome}");
+                game.on_end(&outcome);
+                self.log_round(&name, &outcome);
+                if self.adaptive {
+                    self.ratings.update(&name, &outcome);
+                }
+                self.last_game = self.clock;
+                self.cooldown = self.cooldown_range.next(&mut self.rng);
+                println!("next game in {:.1}s", self.cooldown.as_secs_f32());
+                self.playing = false;
+                if self.pending.is_some() {
+                    self.apply_pending();
+                } else {
+                    self.select_game();
+                }
+                return Some(outcome);
             }
         }
         None
     }
+
+    // ends a running game without a result, e.g. when quitting
+    pub fn abort(&mut self) -> Option<GameOutcome> {
+        if !self.playing {
+            return None;
+        }
+        self.playing = false;
+        let outcome = GameOutcome::Aborted(RoundStats {
+            score: 0,
+            duration: self.round_time,
+            progress: 0.0,
+            metadata: vec![],
+        });
+        if let Some(index) = self.selected {
+            let game = &mut self.game_list[index];
+            let name = game.get_info().to_string();
+            println!("{name} finished: {outcome}");
+            game.on_end(&outcome);
+            self.log_round(&name, &outcome);
+        }
+        Some(outcome)
+    }
+
+    fn log_round(&self, name: &str, outcome: &GameOutcome) {
+        if let Some(history) = &self.history {
+            let settings = history::settings_snapshot(&self.settings, name);
+            let round = Round::new(self.clock, name, settings, outcome, self.reaction);
+            if let Err(e) = history.append(&round) {
+                println!("could not write the round to the history: {e}");
+            }
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct RoundStats {
+    pub score: i32,
+    pub duration: Duration,
+    // how close the player got to winning, 0.0 to 1.0
+    pub progress: f32,
+    // game specific numbers like circles clicked or bounces survived
+    pub metadata: Vec<(&'static str, f32)>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum GameOutcome {
+    Won(RoundStats),
+    Lost(RoundStats),
+    // stopped before there was a result
+    Aborted(RoundStats),
+    // the time limit ran out before the goal was reached
+    TimedOut(RoundStats),
+}
+
+impl GameOutcome {
+    pub fn stats(&self) -> &RoundStats {
+        match self {
+            GameOutcome::Won(stats)
+            | GameOutcome::Lost(stats)
+            | GameOutcome::Aborted(stats)
+            | GameOutcome::TimedOut(stats) => stats,
+        }
+    }
+
+    pub fn is_loss(&self) -> bool {
+        matches!(self, GameOutcome::Lost(_) | GameOutcome::TimedOut(_))
+    }
+
+    // how badly the round went, 0.0 for anything but a loss up to 1.0 for a loss without any progress
+    pub fn severity(&self) -> f32 {
+        if self.is_loss() {
+            1.0 - self.stats().progress.clamp(0.0, 1.0)
+        } else {
+            0.0
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            GameOutcome::Won(_) => "won",
+            GameOutcome::Lost(_) => "lost",
+            GameOutcome::Aborted(_) => "aborted",
+            GameOutcome::TimedOut(_) => "timed out",
+        }
+    }
+}
+
+impl std::fmt::Display for GameOutcome {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        let stats = self.stats();
+        write!(
+            f,
+            "{} after {:.1}s, score {}",
+            self.name(),
+            stats.duration.as_secs_f32(),
+            stats.score
+        )?;
+        for (key, value) in &stats.metadata {
+            write!(f, ", {key} {value}")?;
+        }
+        Ok(())
+    }
+}
+
+// what a game gets to know when a round starts
+pub struct GameContext<'a> {
+    // where the game is played, in window coordinates
+    pub area: Rectangle,
+    // the only source of randomness, games that need it later fork their own from it
+    pub rng: &'a mut StdRng,
 }
 
 pub trait Game {
     // gets name of the game
     fn get_info(&mut self) -> &str;
-    // main logic of the game
-    fn logic(&mut self, mouse_pos: Vector2, delta_time: f32);
-    // drawing the frame of the game
-    fn draw(&mut self, d: &mut RaylibDrawHandle);
-    // None if still going else return if lost
-    fn is_finished(&mut self) -> Option<bool>;
+    // a round is about to be shown, start over from a fresh random state
+    fn on_start(&mut self, ctx: &mut GameContext);
+    // the round is over, called once with the result from is_finished
+    fn on_end(&mut self, outcome: &GameOutcome);
+    // level from 0.0 (the configured values) to 1.0 (hardest), set before on_start
+    fn set_difficulty(&mut self, level: f32);
+    // the player stepped away, logic is not called until resume
+    fn pause(&mut self) {}
+    fn resume(&mut self) {}
+    // main logic of the game, the cursor in input is in window coordinates
+    fn logic(&mut self, input: &InputState, delta_time: f32);
+    // drawing the frame of the game, alpha is how far the frame is between the
+    // previous logic step (0.0) and the latest one (1.0)
+    fn draw(&mut self, d: &mut dyn Canvas, alpha: f32);
+    // None while the game is still going
+    fn is_finished(&mut self) -> Option<GameOutcome>;
 }
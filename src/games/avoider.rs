@@ -1,9 +1,14 @@
-use crate::game_handler::Game;
+use crate::canvas::Canvas;
+use crate::config::AvoiderConfig;
+use crate::game_handler::{Game, GameContext, GameOutcome, RoundStats};
+use crate::input::InputState;
 use crate::util::Timer;
-use yaml_rust2::Yaml;
-use rand::{Rng, rngs::ThreadRng};
-use std::{ffi::CString, time::Duration};
-use {raylib::ffi::MeasureText, raylib::prelude::*};
+use rand::{Rng, SeedableRng, rngs::StdRng};
+use raylib::prelude::*;
+use std::time::Duration;
+
+// pixels per second
+const ENEMY_SPEED: f32 = 500.0;
 
 pub struct Avoider {
     player_pos: Vector2,
@@ -13,32 +18,40 @@ pub struct Avoider {
     game_size: Rectangle,
     timer: Timer,
     spawn_timer: Timer,
-    rng: ThreadRng,
-    lost: bool
+    // forked from the handler's rng every round
+    rng: StdRng,
+    lost: bool,
+    // enemies that left the screen without hitting the player
+    dodged: i32,
+    // player position before the last logic step and its length, drawing blends between steps
+    previous_player: Vector2,
+    last_step: f32,
+    // timer lengths from the config, the difficulty level scales them
+    base_time: Duration,
+    base_spawn: Duration,
 }
 
 impl Avoider {
-    pub fn new(screen_size: (i32, i32), settings: &Vec<Yaml>) -> Avoider {
-        let game_rect = Rectangle {
-            x: 0.0,
-            y: 0.0,
-            width: screen_size.0 as f32,
-            height: screen_size.1 as f32,
-        };
-        
-        let time_length = settings[1].as_f64().unwrap();
-        let spawn_timer = settings[2].as_f64().unwrap();
+    pub fn new(config: &AvoiderConfig) -> Avoider {
+        let time_length = config.time_limit;
+        let spawn_timer = config.spawn_timer;
 
+        // the player is placed by on_start
         Avoider {
-            player_pos: Vector2::new((screen_size.0 / 2) as f32, (screen_size.1 / 2) as f32),
+            player_pos: Vector2::zero(),
             player_size: 20.0,
             enemies: vec![],
             enemy_size: 30.0,
-            game_size: game_rect,
-            timer: Timer::new(Duration::from_secs_f64(time_length)),
-            spawn_timer: Timer::new(Duration::from_secs_f64(spawn_timer)),
-            rng: rand::rng(),
+            game_size: Rectangle::new(0.0, 0.0, 0.0, 0.0),
+            timer: Timer::new(Duration::from_secs_f32(time_length)),
+            spawn_timer: Timer::new(Duration::from_secs_f32(spawn_timer)),
+            rng: StdRng::seed_from_u64(0),
             lost: false,
+            dodged: 0,
+            previous_player: Vector2::zero(),
+            last_step: 0.0,
+            base_time: Duration::from_secs_f32(time_length),
+            base_spawn: Duration::from_secs_f32(spawn_timer),
         }
     }
 
@@ -50,15 +63,15 @@ impl Avoider {
         let start_side = self.rng.random_range(0..4);
         let start_cord =  self.rng.random_range(sides[start_side % 2].0..sides[start_side % 2].1);
 
-        let mut start_pos = Vector2::zero();
+        let mut start_pos = Vector2::new(self.game_size.x, self.game_size.y);
         if start_side == 0 {
             start_pos.x = start_cord;
         } else if start_side == 1 {
-            start_pos.x = self.game_size.width;
+            start_pos.x = self.game_size.x + self.game_size.width;
             start_pos.y = start_cord;
         } else if start_side == 2 {
             start_pos.x = start_cord;
-            start_pos.y = self.game_size.height;
+            start_pos.y = self.game_size.y + self.game_size.height;
         } else if start_side == 3 {
             start_pos.y = start_cord;
         }        
@@ -70,7 +83,6 @@ impl Avoider {
     }
 
     fn move_enemies(&mut self, delta_time: f32) {
-        let enemy_speed = 500.0;
         let enemies = self.enemies.clone();
         let mut updated_enemies: Vec<(Vector2, Vector2)> = vec![];
 
@@ -82,18 +94,21 @@ impl Avoider {
                enemy.0.x < self.game_size.x ||
                enemy.0.y > self.game_size.y + self.game_size.height ||
                enemy.0.y < self.game_size.y {
+                self.dodged += 1;
                 continue;
             }
-            let movement = enemy.1 * enemy_speed * delta_time;
+            let movement = enemy.1 * ENEMY_SPEED * delta_time;
 
             let new_pos = enemy.0 + movement;
 
             updated_enemies.push((new_pos, enemy.1));
         }
-        (*self).enemies = updated_enemies;
+        self.enemies = updated_enemies;
     }
 
     fn avoider_logic(&mut self, mouse_pos: Vector2, delta_time: f32) {
+        self.previous_player = self.player_pos;
+        self.last_step = delta_time;
         self.player_pos = self.player_pos.lerp(mouse_pos, delta_time * 15.0);
 
         self.timer.update(delta_time);
@@ -106,65 +121,36 @@ impl Avoider {
         self.move_enemies(delta_time);
     }
 
-    fn draw_frame(&mut self, d: &mut RaylibDrawHandle) {
+    fn draw_frame(&mut self, d: &mut dyn Canvas, alpha: f32) {
+        // enemies move in a straight line, step them back to where they were at alpha
+        let behind = ENEMY_SPEED * self.last_step * (1.0 - alpha);
         for enemy in self.enemies.clone() {
-            d.draw_circle(
-                enemy.0.x as i32,
-                enemy.0.y as i32,
-                self.enemy_size,
-                Color::BLACK,
-            );
-            d.draw_circle(
-                enemy.0.x as i32,
-                enemy.0.y as i32,
-                self.enemy_size - 4.0,
-                Color::RED,
-            );
+            let pos = enemy.0 - enemy.1 * behind;
+            d.draw_circle(pos.x as i32, pos.y as i32, self.enemy_size, Color::BLACK);
+            d.draw_circle(pos.x as i32, pos.y as i32, self.enemy_size - 4.0, Color::RED);
         }
 
+        let player_pos = self.previous_player.lerp(self.player_pos, alpha);
         d.draw_circle(
-            self.player_pos.x as i32,
-            self.player_pos.y as i32,
+            player_pos.x as i32,
+            player_pos.y as i32,
             self.player_size,
             Color::BLACK,
         );
         d.draw_circle(
-            self.player_pos.x as i32,
-            self.player_pos.y as i32,
+            player_pos.x as i32,
+            player_pos.y as i32,
             self.player_size - 3.4,
             Color::GREENYELLOW,
         );
 
-        unsafe {
-            let time_left = self.timer.time_left();
-            let text = CString::new(format!("{:?}", time_left as i32)).unwrap();
-            d.draw_text(
-                &format!("{}", text.to_str().unwrap()),
-                (self.game_size.x * 2.0 + self.game_size.width) as i32 / 2
-                    - (MeasureText(text.as_ptr() as *const i8, 50) as i32 / 2)
-                    + 2,
-                self.game_size.y as i32 + 11,
-                50,
-                Color::BLACK,
-            );
-            d.draw_text(
-                &format!("{}", text.to_str().unwrap()),
-                (self.game_size.x * 2.0 + self.game_size.width) as i32 / 2
-                    - (MeasureText(text.as_ptr() as *const i8, 50) as i32 / 2)
-                    - 2,
-                self.game_size.y as i32 + 9,
-                50,
-                Color::BLACK,
-            );
-            d.draw_text(
-                &format!("{}", text.to_str().unwrap()),
-                (self.game_size.x * 2.0 + self.game_size.width) as i32 / 2
-                    - (MeasureText(text.as_ptr() as *const i8, 50) as i32 / 2),
-                self.game_size.y as i32 + 10,
-                50,
-                Color::RED,
-            );
-        }
+        let time_left = self.timer.time_left();
+        let text = format!("{:?}", time_left);
+        let x = (self.game_size.x * 2.0 + self.game_size.width) as i32 / 2
+            - d.measure_text(&text, 50) / 2;
+        d.draw_text(&text, x + 2, self.game_size.y as i32 + 11, 50, Color::BLACK);
+        d.draw_text(&text, x - 2, self.game_size.y as i32 + 9, 50, Color::BLACK);
+        d.draw_text(&text, x, self.game_size.y as i32 + 10, 50, Color::RED);
     }
 }
 
@@ -173,24 +159,63 @@ impl Game for Avoider {
         "Avoider" as &str
     }
 
-    fn logic(&mut self, mouse_pos: Vector2, delta_time: f32) {
-        self.avoider_logic(mouse_pos, delta_time);
+    fn on_start(&mut self, ctx: &mut GameContext) {
+        let area = ctx.area;
+        self.rng = StdRng::from_rng(ctx.rng);
+        self.game_size = area;
+        self.player_pos = Vector2::new(area.x + area.width / 2.0, area.y + area.height / 2.0);
+        self.previous_player = self.player_pos;
+        self.last_step = 0.0;
+        self.enemies.clear();
+        self.timer.reset();
+        self.spawn_timer.reset();
+        self.lost = false;
+        self.dodged = 0;
+    }
+
+    fn on_end(&mut self, _outcome: &GameOutcome) {
+        self.enemies.clear();
     }
 
-    fn draw(&mut self, d: &mut RaylibDrawHandle) {
-        self.draw_frame(d);
+    fn set_difficulty(&mut self, level: f32) {
+        // at the top level enemies come twice as often and the round lasts half again as long
+        self.spawn_timer.set_duration(self.base_spawn.mul_f32(1.0 - 0.5 * level));
+        self.timer.set_duration(self.base_time.mul_f32(1.0 + 0.5 * level));
     }
 
-    fn is_finished(&mut self) -> Option<bool> {
-        if self.timer.is_finished()  {
-            (*self).timer.reset();
-            self.enemies.clear();
-            Some(false)
-        } else if self.lost {
-            self.lost = false;
-            (*self).timer.reset();
-            self.enemies.clear();
-            Some(true)
+    fn pause(&mut self) {
+        self.timer.pause();
+        self.spawn_timer.pause();
+    }
+
+    fn resume(&mut self) {
+        self.timer.resume();
+        self.spawn_timer.resume();
+    }
+
+    fn logic(&mut self, input: &InputState, delta_time: f32) {
+        self.avoider_logic(input.cursor, delta_time);
+    }
+
+    fn draw(&mut self, d: &mut dyn Canvas, alpha: f32) {
+        self.draw_frame(d, alpha);
+    }
+
+    fn is_finished(&mut self) -> Option<GameOutcome> {
+        if self.timer.is_finished() || self.lost {
+            let survived = self.timer.elapsed().min(self.timer.duration());
+            let stats = RoundStats {
+                score: survived.as_secs() as i32,
+                duration: survived,
+                progress: survived.as_secs_f32() / self.timer.duration().as_secs_f32().max(0.001),
+                metadata: vec![("enemies_dodged", self.dodged as f32)],
+            };
+            // running out the clock wins even if an enemy hit on the last frame
+            if self.timer.is_finished() {
+                Some(GameOutcome::Won(stats))
+            } else {
+                Some(GameOutcome::Lost(stats))
+            }
         } else {
             None
         }
@@ -1,26 +1,33 @@
-use crate::{game_handler::Game, util::Timer};
-use mki::Mouse;
-use yaml_rust2::Yaml;
+use crate::{
+    canvas::Canvas,
+    config::CirclesConfig,
+    game_handler::{Game, GameContext, GameOutcome, RoundStats},
+    input::InputState,
+    platform::Button,
+    util::Timer,
+};
 use rand::prelude::*;
-use raylib::{ffi::MeasureText, prelude::*};
-use std::{ffi::CString, time::Duration};
+use raylib::prelude::*;
+use std::time::Duration;
 
 pub struct Circles {
     timer: Timer,
     amount: i32,
+    min_amount: i32,
     max_amount: i32,
+    // time limit from the config
+    base_time: Duration,
     circles: Vec<(Vector2, f32)>,
     game_size: Rectangle,
     lost: bool,
 }
 
-fn place_circles(amount: i32, rect: Rectangle) -> Vec<(Vector2, f32)> {
+fn place_circles(amount: i32, rect: Rectangle, rng: &mut impl Rng) -> Vec<(Vector2, f32)> {
     let mut circle_vec: Vec<(Vector2, f32)> = Vec::new();
     let circle_size: f32 = 50.0;
-    let mut rng = rand::rng();
     for _i in 0..amount {
-        let mut random_x = rng.random_range(rect.x..rect.width);
-        let mut random_y = rng.random_range(rect.y..rect.height);
+        let mut random_x = rng.random_range(rect.x..rect.x + rect.width);
+        let mut random_y = rng.random_range(rect.y..rect.y + rect.height);
         for circle in &circle_vec {
             if circle.0.distance_to(Vector2::new(random_x, random_y)) < circle_size * 2.0 {
                 //new vector pos away from circle
@@ -35,78 +42,61 @@ fn place_circles(amount: i32, rect: Rectangle) -> Vec<(Vector2, f32)> {
     circle_vec
 }
 
+// inner 80% of the area so circles never touch the monitor edge
+fn play_area(area: Rectangle) -> Rectangle {
+    Rectangle {
+        x: area.x + area.width * 0.1,
+        y: area.y + area.height * 0.1,
+        width: area.width * 0.8,
+        height: area.height * 0.8,
+    }
+}
+
 impl Circles {
-    pub fn new(screen_size: (i32, i32), settings: &Vec<Yaml>) -> Circles {
-        let game_rect = Rectangle {
-            x: screen_size.0 as f32 * 0.1,
-            y: screen_size.1 as f32 * 0.1,
-            width: screen_size.0 as f32 * 0.8,
-            height: screen_size.1 as f32 * 0.8,
-        };
-        
-        let amount = settings[1].as_i64().unwrap() as i32;
-        let max_amount = settings[2].as_i64().unwrap() as i32;
-        let time = settings[3].as_f64().unwrap();
-        let vec_circles = place_circles(amount, game_rect);
+    pub fn new(config: &CirclesConfig) -> Circles {
+        let amount = config.min_amount.min(config.max_amount);
+        let max_amount = config.max_amount;
+        let time = config.time_limit;
 
+        // circles are placed by on_start
         Circles {
-            timer: Timer::new(Duration::from_secs_f64(time)),
-            amount: amount,
-            max_amount: max_amount,
-            circles: (vec_circles),
-            game_size: game_rect,
+            timer: Timer::new(Duration::from_secs_f32(time)),
+            amount,
+            min_amount: amount,
+            max_amount,
+            base_time: Duration::from_secs_f32(time),
+            circles: vec![],
+            game_size: Rectangle::new(0.0, 0.0, 0.0, 0.0),
             lost: false,
         }
     }
 
-    fn circle_logic(&mut self, mouse_pos: Vector2, _delta_time: f32) {
+    fn circle_logic(&mut self, input: &InputState, _delta_time: f32) {
         self.timer.update(_delta_time);
 
         if self.timer.is_finished() {
             self.lost = true;
         }
 
-        if Mouse::Left.is_pressed() {
+        // only the click itself counts, holding the button and sweeping does not
+        if input.button(Button::Left).pressed {
             self.circles
-                .retain(|(pos, radius)| pos.distance_to(mouse_pos) > *radius);
+                .retain(|(pos, radius)| pos.distance_to(input.cursor) > *radius);
         }
     }
 
-    fn draw_frame(&mut self, d: &mut RaylibDrawHandle) {
+    fn draw_frame(&mut self, d: &mut dyn Canvas) {
         let cirlces = self.circles.clone();
         for circle in cirlces {
             d.draw_circle(circle.0.x as i32, circle.0.y as i32, circle.1, Color::RED);
         }
-        unsafe {
-            let time_left = self.timer.time_left();
-            let text = CString::new(format!("{:?}", time_left as i32)).unwrap();
-            d.draw_text(
-                &format!("{}", text.to_str().unwrap()),
-                (self.game_size.x * 2.0 + self.game_size.width) as i32 / 2
-                    - (MeasureText(text.as_ptr() as *const i8, 50) as i32 / 2)
-                    + 2,
-                self.game_size.y as i32 + 1,
-                50,
-                Color::BLACK,
-            );
-            d.draw_text(
-                &format!("{}", text.to_str().unwrap()),
-                (self.game_size.x * 2.0 + self.game_size.width) as i32 / 2
-                    - (MeasureText(text.as_ptr() as *const i8, 50) as i32 / 2)
-                    - 2,
-                self.game_size.y as i32 - 1,
-                50,
-                Color::BLACK,
-            );
-            d.draw_text(
-                &format!("{}", text.to_str().unwrap()),
-                (self.game_size.x * 2.0 + self.game_size.width) as i32 / 2
-                    - (MeasureText(text.as_ptr() as *const i8, 50) as i32 / 2),
-                self.game_size.y as i32,
-                50,
-                Color::RED,
-            );
-        }
+        let time_left = self.timer.time_left();
+        let text = format!("{:?}", time_left);
+        let x = (self.game_size.x * 2.0 + self.game_size.width) as i32 / 2
+            - d.measure_text(&text, 50) / 2;
+        d.draw_text(&text, x + 2, self.game_size.y as i32 + 1, 50, Color::BLACK);
+        d.draw_text(&text, x - 2, self.game_size.y as i32 - 1, 50, Color::BLACK);
+        d.draw_text(&text, x, self.game_size.y as i32, 50, Color::RED);
     }
 }
 
@@ -115,23 +105,55 @@ impl Game for Circles {
         "Circles" as &str
     }
 
-    fn logic(&mut self, mouse_pos: Vector2, delta_time: f32) {
-        self.circle_logic(mouse_pos, delta_time);
+    fn on_start(&mut self, ctx: &mut GameContext) {
+        self.game_size = play_area(ctx.area);
+        self.circles = place_circles(self.amount, self.game_size, ctx.rng);
+        self.timer.reset();
+        self.lost = false;
+    }
+
+    fn on_end(&mut self, _outcome: &GameOutcome) {}
+
+    fn set_difficulty(&mut self, level: f32) {
+        // from min_amount circles at level 0 up to max_amount, with a fifth less time at the top
+        let extra = ((self.max_amount - self.min_amount).max(0) as f32 * level).round() as i32;
+        self.amount = (self.min_amount + extra).clamp(1, self.max_amount.max(1));
+        self.timer.set_duration(self.base_time.mul_f32(1.0 - 0.2 * level));
+    }
+
+    fn pause(&mut self) {
+        self.timer.pause();
+    }
+
+    fn resume(&mut self) {
+        self.timer.resume();
     }
 
-    fn draw(&mut self, d: &mut RaylibDrawHandle) {
+    fn logic(&mut self, input: &InputState, delta_time: f32) {
+        self.circle_logic(input, delta_time);
+    }
+
+    fn draw(&mut self, d: &mut dyn Canvas, _alpha: f32) {
         self.draw_frame(d);
     }
 
-    fn is_finished(&mut self) -> Option<bool> {
-        if self.circles.len() < 1 || self.lost {
-            let amount = (self.amount + 1).clamp(1, self.max_amount);
-            self.amount = amount;
-            self.circles = place_circles(amount, self.game_size);
-            self.timer.reset();
-            let lost = self.lost;
-            self.lost = false;
-            Some(lost)
+    fn is_finished(&mut self) -> Option<GameOutcome> {
+        if self.circles.is_empty() || self.lost {
+            let clicked = self.amount - self.circles.len() as i32;
+            let stats = RoundStats {
+                score: clicked,
+                duration: self.timer.elapsed(),
+                progress: clicked as f32 / self.amount.max(1) as f32,
+                metadata: vec![
+                    ("circles_clicked", clicked as f32),
+                    ("circles_total", self.amount as f32),
+                ],
+            };
+            if self.lost {
+                Some(GameOutcome::TimedOut(stats))
+            } else {
+                Some(GameOutcome::Won(stats))
+            }
         } else {
             None
         }
@@ -1,41 +1,48 @@
-use crate::game_handler::Game;
-use mki::Keyboard;
-use yaml_rust2::Yaml;
+use crate::canvas::Canvas;
+use crate::config::PongConfig;
+use crate::game_handler::{Game, GameContext, GameOutcome, RoundStats};
+use crate::input::InputState;
+use crate::platform::Key;
 use rand::prelude::*;
 use raylib::prelude::*;
+use std::time::Duration;
 
 #[derive(Copy, Clone)]
 pub struct Pong {
     ball_size: f32,
     ball_pos: Vector2,
     ball_speed: Vector2,
+    // ball speed from the config, ball_speed changes during a round
+    base_speed: f32,
+    // ai paddle speed from the config
+    base_ai_speed: f32,
+    // ball speed multiplier from the difficulty level
+    speed_scale: f32,
     paddle1: Vector3,
     paddle2: Vector3,
     paddle_speed: f32,
     ai_paddle_speed: f32,
     game_size: Rectangle,
-    screen_size: (i32, i32),
+    area: Rectangle,
     pub finished: bool,
     lost: bool,
+    // times the player returned the ball this round
+    bounces: i32,
+    elapsed: f32,
+    // ball position and paddle heights before the last logic step, drawing blends from them
+    previous: (Vector2, f32, f32),
 }
 
 impl Pong {
-    pub fn new(screen_size: (i32, i32), settings: &Vec<Yaml>) -> Pong {
-        let game_size: (i32, i32) = (800, 400);
-        let game_rect = Rectangle {
-            x: ((screen_size.0 / 2) - (game_size.0 / 2)) as f32,
-            y: ((screen_size.1 / 2) - (game_size.1 / 2)) as f32,
-            width: game_size.0 as f32,
-            height: game_size.1 as f32,
-        };
-
+    pub fn new(config: &PongConfig) -> Pong {
+        // the field is placed by on_start
         Pong {
             ball_size: 12.0,
-            ball_pos: Vector2 {
-                x: game_rect.x + (game_rect.width / 2.0),
-                y: game_rect.y + (game_rect.height / 2.0),
-            },
-            ball_speed: Vector2 { x: settings[1].as_f64().unwrap() as f32 * 1.2, y: settings[1].as_f64().unwrap() as f32 },
+            ball_pos: Vector2::zero(),
+            ball_speed: Vector2 { x: config.ball_speed * 1.2, y: config.ball_speed },
+            base_speed: config.ball_speed,
+            base_ai_speed: config.ai_paddle_speed,
+            speed_scale: 1.0,
             paddle1: Vector3 {
                 x: 100.0,
                 y: 0.0,
@@ -46,20 +53,19 @@ impl Pong {
                 y: 0.0,
                 z: 20.0,
             },
-            paddle_speed: settings[2].as_f64().unwrap() as f32,
-            ai_paddle_speed: settings[3].as_f64().unwrap() as f32,
-            game_size: game_rect,
-            screen_size: screen_size,
+            paddle_speed: config.paddle_speed,
+            ai_paddle_speed: config.ai_paddle_speed,
+            game_size: Rectangle::new(0.0, 0.0, 0.0, 0.0),
+            area: Rectangle::new(0.0, 0.0, 0.0, 0.0),
             finished: false,
             lost: false,
+            bounces: 0,
+            elapsed: 0.0,
+            previous: (Vector2::zero(), 0.0, 0.0),
         }
     }
 
-    fn reset(&mut self) {
-        self.ball_pos = Vector2 {
-            x: self.game_size.x + (self.game_size.width / 2.0),
-            y: self.game_size.y + (self.game_size.height / 2.0),
-        };
+    fn reset(&mut self, rng: &mut impl Rng) {
         self.paddle1 = Vector3 {
             x: self.paddle1.x,
             y: 0.0,
@@ -71,31 +77,46 @@ impl Pong {
             z: 20.0,
         };
 
-        let mut rng = rand::rng();
-        let game_size_x = rng.random_range(800..(self.screen_size.0 as f32 * 0.8) as i32);
-        let game_size_y = rng.random_range(400..(self.screen_size.1 as f32 * 0.8) as i32);
+        // small monitors still get the minimum 800x400 field
+        let game_size_x = rng.random_range(800..((self.area.width * 0.8) as i32).max(801));
+        let game_size_y = rng.random_range(400..((self.area.height * 0.8) as i32).max(401));
 
         let game_rect = Rectangle {
-            x: ((self.screen_size.0 / 2) - (game_size_x / 2)) as f32,
-            y: ((self.screen_size.1 / 2) - (game_size_y / 2)) as f32,
+            x: self.area.x + ((self.area.width as i32 / 2) - (game_size_x / 2)) as f32,
+            y: self.area.y + ((self.area.height as i32 / 2) - (game_size_y / 2)) as f32,
             width: game_size_x as f32,
             height: game_size_y as f32,
         };
 
         self.game_size = game_rect;
+        // center the ball in the new field, serving towards the ai at a random angle
+        self.ball_pos = Vector2 {
+            x: self.game_size.x + (self.game_size.width / 2.0),
+            y: self.game_size.y + (self.game_size.height / 2.0),
+        };
+        let vertical = if rng.random_bool(0.5) { 1.0 } else { -1.0 };
+        let speed = self.base_speed * self.speed_scale;
+        self.ball_speed = Vector2 {
+            x: speed * 1.2,
+            y: speed * vertical,
+        };
         self.finished = false;
+        self.lost = false;
+        self.bounces = 0;
+        self.elapsed = 0.0;
+        self.previous = (self.ball_pos, self.paddle1.y, self.paddle2.y);
     }
 
-    fn pong_logic(&mut self, delta_time: f32) {
+    fn pong_logic(&mut self, input: &InputState, delta_time: f32) {
         self.pong_ball(delta_time);
 
         // paddle1 controls
-        if Keyboard::Up.is_pressed()
+        if input.key(Key::Up).held
             && self.paddle1.y as i32 - self.paddle1.x as i32 / 2
-                > self.game_size.height as i32 / 2 * -1
+                > -(self.game_size.height as i32 / 2)
         {
             self.paddle1.y -= self.paddle_speed * delta_time;
-        } else if Keyboard::Down.is_pressed()
+        } else if input.key(Key::Down).held
             && self.paddle1.y as i32 + self.paddle1.x as i32 / 2 < self.game_size.height as i32 / 2
         {
             self.paddle1.y += self.paddle_speed * delta_time;
@@ -127,11 +148,15 @@ impl Pong {
             self.paddle2.x,
         );
 
-        let ball_speed = 300.0;
+        let ball_speed = self.base_speed * self.speed_scale;
         if paddle1rec.check_collision_circle_rec(self.ball_pos, self.ball_size) {
+            // the ball overlaps the paddle for a few frames, only count the first one
+            if self.ball_speed.x < 0.0 {
+                self.bounces += 1;
+            }
             self.ball_speed.x = ball_speed;
         } else if paddle2rec.check_collision_circle_rec(self.ball_pos, self.ball_size) {
-            self.ball_speed.x = ball_speed * -1.0;
+            self.ball_speed.x = -ball_speed;
         } else if self.ball_pos.x >= (self.game_size.x + self.game_size.width - self.ball_size)
         /* if hits right side */
         {
@@ -150,7 +175,7 @@ impl Pong {
         }
     }
 
-    fn draw_paddles(self, d: &mut RaylibDrawHandle) {
+    fn draw_paddles(self, d: &mut dyn Canvas) {
         d.draw_rectangle(
             self.game_size.x as i32 + 15,
             self.game_size.y as i32 + self.game_size.height as i32 / 2 + self.paddle1.y as i32
@@ -169,7 +194,7 @@ impl Pong {
         );
     }
 
-    fn draw_frame(&mut self, d: &mut RaylibDrawHandle) {
+    fn draw_frame(&mut self, d: &mut dyn Canvas) {
         d.draw_rectangle(
             self.game_size.x as i32,
             self.game_size.y as i32,
@@ -194,18 +219,48 @@ impl Game for Pong {
         "Pong" as &str
     }
 
-    fn logic(&mut self, _mouse_pos: Vector2, delta_time: f32) {
-        self.pong_logic(delta_time);
+    fn on_start(&mut self, ctx: &mut GameContext) {
+        self.area = ctx.area;
+        self.reset(ctx.rng);
+    }
+
+    fn on_end(&mut self, _outcome: &GameOutcome) {}
+
+    fn set_difficulty(&mut self, level: f32) {
+        // up to half again as fast for the ball and the ai
+        self.speed_scale = 1.0 + 0.5 * level;
+        self.ai_paddle_speed = self.base_ai_speed * (1.0 + 0.5 * level);
+    }
+
+    fn logic(&mut self, input: &InputState, delta_time: f32) {
+        self.previous = (self.ball_pos, self.paddle1.y, self.paddle2.y);
+        self.elapsed += delta_time;
+        self.pong_logic(input, delta_time);
     }
 
-    fn draw(&mut self, d: &mut RaylibDrawHandle) {
-        self.draw_frame(d);
+    fn draw(&mut self, d: &mut dyn Canvas, alpha: f32) {
+        let (ball_pos, paddle1, paddle2) = self.previous;
+        let mut shown = *self;
+        shown.ball_pos = ball_pos.lerp(self.ball_pos, alpha);
+        shown.paddle1.y = paddle1 + (self.paddle1.y - paddle1) * alpha;
+        shown.paddle2.y = paddle2 + (self.paddle2.y - paddle2) * alpha;
+        shown.draw_frame(d);
     }
 
-    fn is_finished(&mut self) -> Option<bool> {
+    fn is_finished(&mut self) -> Option<GameOutcome> {
         if self.finished {
-            (&mut *self).reset();
-            Some(self.lost)
+            let stats = RoundStats {
+                score: self.bounces,
+                duration: Duration::from_secs_f32(self.elapsed),
+                // five returns counts as a close game
+                progress: (self.bounces as f32 / 5.0).min(1.0),
+                metadata: vec![("bounces", self.bounces as f32)],
+            };
+            if self.lost {
+                Some(GameOutcome::Lost(stats))
+            } else {
+                Some(GameOutcome::Won(stats))
+            }
         } else {
             None
         }
@@ -0,0 +1,423 @@
+use crate::config::Config;
+use crate::game_handler::GameOutcome;
+use chrono::{DateTime, FixedOffset, Local, NaiveDate};
+use serde_json::{Value, json};
+use std::collections::BTreeMap;
+use std::fmt::Write as _;
+use std::fs::OpenOptions;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+// one played round, a line of the history file
+#[derive(Clone, Debug)]
+pub struct Round {
+    pub time: DateTime<FixedOffset>,
+    pub game: String,
+    // the game's section of settings.yaml as it was for this round
+    pub settings: Value,
+    // won, lost, aborted or timed out
+    pub outcome: String,
+    pub duration: Duration,
+    pub score: i32,
+    pub progress: f32,
+    // time from the game showing up to the first click or key press
+    pub reaction_time: Option<Duration>,
+    pub metadata: Vec<(String, f32)>,
+}
+
+// the settings a game was played with, so old rounds stay comparable after changes
+pub fn settings_snapshot(config: &Config, game: &str) -> Value {
+    match game {
+        "Pong" => json!({
+            "weight": config.pong.weight,
+            "ball_speed": config.pong.ball_speed,
+            "paddle_speed": config.pong.paddle_speed,
+            "ai_paddle_speed": config.pong.ai_paddle_speed,
+        }),
+        "Circles" => json!({
+            "weight": config.circles.weight,
+            "min_amount": config.circles.min_amount,
+            "max_amount": config.circles.max_amount,
+            "time_limit": config.circles.time_limit,
+        }),
+        "Avoider" => json!({
+            "weight": config.avoider.weight,
+            "time_limit": config.avoider.time_limit,
+            "spawn_timer": config.avoider.spawn_timer,
+        }),
+        _ => json!({}),
+    }
+}
+
+impl Round {
+    pub fn new(
+        time: DateTime<Local>,
+        game: &str,
+        settings: Value,
+        outcome: &GameOutcome,
+        reaction_time: Option<Duration>,
+    ) -> Round {
+        let stats = outcome.stats();
+        Round {
+            time: time.fixed_offset(),
+            game: game.to_string(),
+            settings,
+            outcome: outcome.name().to_string(),
+            duration: stats.duration,
+            score: stats.score,
+            progress: stats.progress,
+            reaction_time,
+            metadata: stats.metadata.iter().map(|(key, value)| (key.to_string(), *value)).collect(),
+        }
+    }
+
+    fn to_json(&self) -> Value {
+        let metadata: serde_json::Map<String, Value> =
+            self.metadata.iter().map(|(key, value)| (key.clone(), json!(value))).collect();
+        json!({
+            "time": self.time.to_rfc3339(),
+            "game": self.game,
+            "settings": self.settings,
+            "outcome": self.outcome,
+            "duration": self.duration.as_secs_f32(),
+            "score": self.score,
+            "progress": self.progress,
+            "reaction_time": self.reaction_time.map(|time| time.as_secs_f32()),
+            "metadata": metadata,
+        })
+    }
+
+    fn from_json(value: &Value) -> Option<Round> {
+        let seconds = |key: &str| value[key].as_f64().map(|v| Duration::from_secs_f64(v.max(0.0)));
+        Some(Round {
+            time: DateTime::parse_from_rfc3339(value["time"].as_str()?).ok()?,
+            game: value["game"].as_str()?.to_string(),
+            settings: value["settings"].clone(),
+            outcome: value["outcome"].as_str()?.to_string(),
+            duration: seconds("duration")?,
+            score: value["score"].as_i64()? as i32,
+            progress: value["progress"].as_f64().unwrap_or(0.0) as f32,
+            reaction_time: seconds("reaction_time"),
+            metadata: value["metadata"]
+                .as_object()
+                .map(|map| {
+                    map.iter()
+                        .filter_map(|(key, value)| Some((key.clone(), value.as_f64()? as f32)))
+                        .collect()
+                })
+                .unwrap_or_default(),
+        })
+    }
+
+    fn is_win(&self) -> bool {
+        self.outcome == "won"
+    }
+
+    fn is_loss(&self) -> bool {
+        self.outcome == "lost" || self.outcome == "timed out"
+    }
+}
+
+// the history file next to the executable
+pub fn history_path() -> Result<PathBuf, Box<dyn std::error::Error>> {
+    let exe_path = std::env::current_exe()?;
+    let exe_dir = exe_path
+        .parent()
+        .ok_or("Could not get executable directory")?;
+    Ok(exe_dir.join("history.jsonl"))
+}
+
+// rounds stored as JSON Lines, one object per line, only ever appended to
+pub struct History {
+    path: PathBuf,
+}
+
+impl History {
+    pub fn new(path: PathBuf) -> History {
+        History { path }
+    }
+
+    pub fn append(&self, round: &Round) -> io::Result<()> {
+        let mut file = OpenOptions::new().create(true).append(true).open(&self.path)?;
+        writeln!(file, "{}", round.to_json())
+    }
+
+    // every readable round, lines that do not parse are counted and skipped, a missing file has no rounds
+    pub fn load(path: &Path) -> io::Result<(Vec<Round>, usize)> {
+        let contents = match std::fs::read_to_string(path) {
+            Ok(contents) => contents,
+            // nothing is written before the first round ends
+            Err(e) if e.kind() == io::ErrorKind::NotFound => String::new(),
+            Err(e) => return Err(e),
+        };
+        let mut rounds = vec![];
+        let mut skipped = 0;
+        for line in contents.lines().filter(|line| !line.trim().is_empty()) {
+            match serde_json::from_str(line).ok().and_then(|value| Round::from_json(&value)) {
+                Some(round) => rounds.push(round),
+                None => skipped += 1,
+            }
+        }
+        Ok((rounds, skipped))
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum ExportFormat {
+    Csv,
+    Json,
+}
+
+impl ExportFormat {
+    pub fn from_name(name: &str) -> Option<ExportFormat> {
+        match name {
+            "csv" => Some(ExportFormat::Csv),
+            "json" => Some(ExportFormat::Json),
+            _ => None,
+        }
+    }
+}
+
+// FNV-1a of the settings snapshot, rounds with the same hash were played with the same settings
+fn config_hash(settings: &Value) -> String {
+    // serde_json keeps object keys sorted, so equal settings give equal text
+    let mut hash: u64 = 0xcbf29ce484222325;
+    for byte in settings.to_string().bytes() {
+        hash ^= byte as u64;
+        hash = hash.wrapping_mul(0x100000001b3);
+    }
+    format!("{hash:016x}")
+}
+
+fn csv_field(text: &str) -> String {
+    if text.contains([',', '"', '\n']) {
+        format!("\"{}\"", text.replace('"', "\"\""))
+    } else {
+        text.to_string()
+    }
+}
+
+// one row per round played on or after since, for spreadsheets and dashboards
+pub fn export(
+    rounds: &[Round],
+    format: ExportFormat,
+    since: Option<NaiveDate>,
+    out: &mut impl Write,
+) -> io::Result<()> {
+    let rounds = rounds
+        .iter()
+        .filter(|round| since.is_none_or(|since| round.time.date_naive() >= since));
+    let clicks = |round: &Round| {
+        round
+            .metadata
+            .iter()
+            .find(|(key, _)| key == "circles_clicked")
+            .map(|(_, value)| *value as i32)
+    };
+    match format {
+        ExportFormat::Csv => {
+            writeln!(out, "time,game,outcome,duration,score,clicks,config_hash")?;
+            for round in rounds {
+                writeln!(
+                    out,
+                    "{},{},{},{:.3},{},{},{}",
+                    round.time.to_rfc3339(),
+                    csv_field(&round.game),
+                    csv_field(&round.outcome),
+                    round.duration.as_secs_f32(),
+                    round.score,
+                    clicks(round).map(|clicks| clicks.to_string()).unwrap_or_default(),
+                    config_hash(&round.settings)
+                )?;
+            }
+        }
+        ExportFormat::Json => {
+            let rows: Vec<Value> = rounds
+                .map(|round| {
+                    json!({
+                        "time": round.time.to_rfc3339(),
+                        "game": round.game,
+                        "outcome": round.outcome,
+                        "duration": round.duration.as_secs_f32(),
+                        "score": round.score,
+                        "clicks": clicks(round),
+                        "config_hash": config_hash(&round.settings),
+                    })
+                })
+                .collect();
+            serde_json::to_writer_pretty(&mut *out, &rows)?;
+            writeln!(out)?;
+        }
+    }
+    Ok(())
+}
+
+// longest run of wins in a row
+fn longest_streak<'a>(rounds: impl Iterator<Item = &'a Round>) -> usize {
+    let mut longest = 0;
+    let mut current = 0;
+    for round in rounds {
+        if round.is_win() {
+            current += 1;
+            longest = longest.max(current);
+        } else if round.is_loss() {
+            current = 0;
+        }
+    }
+    longest
+}
+
+fn summary_line(name: &str, rounds: &[&Round]) -> String {
+    let wins = rounds.iter().filter(|round| round.is_win()).count();
+    let decided = rounds.iter().filter(|round| round.is_win() || round.is_loss()).count();
+    let win_rate = match decided {
+        0 => "-".to_string(),
+        _ => format!("{:.0}%", wins as f32 / decided as f32 * 100.0),
+    };
+    let reactions: Vec<f32> = rounds
+        .iter()
+        .filter_map(|round| round.reaction_time)
+        .map(|time| time.as_secs_f32())
+        .collect();
+    let reaction = match reactions.len() {
+        0 => "-".to_string(),
+        count => format!("{:.2}s", reactions.iter().sum::<f32>() / count as f32),
+    };
+    format!(
+        "{:<10} {:>6} {:>9} {:>13} {:>15}",
+        name,
+        rounds.len(),
+        win_rate,
+        reaction,
+        longest_streak(rounds.iter().copied())
+    )
+}
+
+// per game win rate, reaction time and streak, then losses per day
+pub fn summarize(rounds: &[Round]) -> String {
+    let mut games: BTreeMap<&str, Vec<&Round>> = BTreeMap::new();
+    let mut losses: BTreeMap<NaiveDate, usize> = BTreeMap::new();
+    for round in rounds {
+        games.entry(round.game.as_str()).or_default().push(round);
+        if round.is_loss() {
+            *losses.entry(round.time.date_naive()).or_default() += 1;
+        }
+    }
+
+    let mut out = String::new();
+    let _ = writeln!(
+        out,
+        "{:<10} {:>6} {:>9} {:>13} {:>15}",
+        "game", "rounds", "win rate", "avg reaction", "longest streak"
+    );
+    for (name, game_rounds) in &games {
+        let _ = writeln!(out, "{}", summary_line(name, game_rounds));
+    }
+    let all: Vec<&Round> = rounds.iter().collect();
+    let _ = writeln!(out, "{}", summary_line("all", &all));
+
+    let _ = writeln!(out, "\nlosses per day");
+    if losses.is_empty() {
+        let _ = writeln!(out, "none");
+    }
+    for (day, count) in &losses {
+        let _ = writeln!(out, "{day}  {count}");
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FIXTURE: &str = r#"{"time":"2025-03-01T09:30:00+01:00","game":"Circles","settings":{"max_amount":10,"min_amount":4,"time_limit":5.0,"weight":1.0},"outcome":"won","duration":2.5,"score":4,"progress":1.0,"reaction_time":0.75,"metadata":{"circles_clicked":4.0,"circles_total":4.0}}
+not json
+{"time":"2025-03-02T18:00:00+01:00","game":"Pong, \"classic\"","settings":{"ball_speed":300.0,"weight":1.0},"outcome":"lost","duration":12.125,"score":3,"progress":0.6,"reaction_time":null,"metadata":{"bounces":3.0}}
+"#;
+
+    // writes the fixture to a file of its own so tests can run in parallel
+    fn load_fixture(name: &str) -> Vec<Round> {
+        let path = std::env::temp_dir().join(format!("history-test-{}-{name}.jsonl", std::process::id()));
+        std::fs::write(&path, FIXTURE).unwrap();
+        let (rounds, skipped) = History::load(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(skipped, 1);
+        rounds
+    }
+
+    fn export_text(rounds: &[Round], format: ExportFormat, since: Option<NaiveDate>) -> String {
+        let mut out = vec![];
+        export(rounds, format, since, &mut out).unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn config_hash_is_fnv1a_of_the_sorted_settings() {
+        // worked out by hand from {"ball_speed":300.0,"weight":1.0}
+        assert_eq!(config_hash(&json!({"weight": 1.0, "ball_speed": 300.0})), "d80aa88855c2f952");
+        assert_eq!(config_hash(&json!({"b": 1, "a": 2})), config_hash(&json!({"a": 2, "b": 1})));
+        assert_ne!(config_hash(&json!({"a": 1})), config_hash(&json!({"a": 2})));
+    }
+
+    #[test]
+    fn csv_quotes_commas_and_quotes() {
+        assert_eq!(csv_field("Pong"), "Pong");
+        assert_eq!(csv_field("a,b"), "\"a,b\"");
+        assert_eq!(csv_field("say \"hi\""), "\"say \"\"hi\"\"\"");
+    }
+
+    #[test]
+    fn export_csv() {
+        let rounds = load_fixture("csv");
+        assert_eq!(
+            export_text(&rounds, ExportFormat::Csv, None),
+            "time,game,outcome,duration,score,clicks,config_hash\n\
+             2025-03-01T09:30:00+01:00,Circles,won,2.500,4,4,f7ae6fa9583a19cf\n\
+             2025-03-02T18:00:00+01:00,\"Pong, \"\"classic\"\"\",lost,12.125,3,,d80aa88855c2f952\n"
+        );
+        let since = NaiveDate::from_ymd_opt(2025, 3, 2);
+        assert_eq!(
+            export_text(&rounds, ExportFormat::Csv, since),
+            "time,game,outcome,duration,score,clicks,config_hash\n\
+             2025-03-02T18:00:00+01:00,\"Pong, \"\"classic\"\"\",lost,12.125,3,,d80aa88855c2f952\n"
+        );
+    }
+
+    #[test]
+    fn export_json() {
+        let rounds = load_fixture("json");
+        assert_eq!(
+            export_text(&rounds, ExportFormat::Json, None),
+            r#"[
+  {
+    "clicks": 4,
+    "config_hash": "f7ae6fa9583a19cf",
+    "duration": 2.5,
+    "game": "Circles",
+    "outcome": "won",
+    "score": 4,
+    "time": "2025-03-01T09:30:00+01:00"
+  },
+  {
+    "clicks": null,
+    "config_hash": "d80aa88855c2f952",
+    "duration": 12.125,
+    "game": "Pong, \"classic\"",
+    "outcome": "lost",
+    "score": 3,
+    "time": "2025-03-02T18:00:00+01:00"
+  }
+]
+"#
+        );
+    }
+
+    #[test]
+    fn missing_history_has_no_rounds() {
+        let path = std::env::temp_dir().join(format!("history-test-{}-missing.jsonl", std::process::id()));
+        let (rounds, skipped) = History::load(&path).unwrap();
+        assert!(rounds.is_empty());
+        assert_eq!(skipped, 0);
+    }
+}
@@ -0,0 +1,114 @@
+use crate::platform::{Button, Key, Platform};
+use raylib::prelude::Vector2;
+
+// in declaration order, states are looked up with key as usize
+pub const KEYS: [Key; 7] = [
+    Key::Up,
+    Key::Down,
+    Key::Left,
+    Key::Right,
+    Key::Space,
+    Key::Escape,
+    Key::F8,
+];
+pub const BUTTONS: [Button; 3] = [Button::Left, Button::Right, Button::Middle];
+
+// state of a key or button this frame
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct ButtonState {
+    // went down this frame
+    pub pressed: bool,
+    // is down this frame, including the frame it was pressed
+    pub held: bool,
+    // went up this frame
+    pub released: bool,
+}
+
+impl ButtonState {
+    fn next(self, down: bool) -> ButtonState {
+        ButtonState {
+            pressed: down && !self.held,
+            held: down,
+            released: !down && self.held,
+        }
+    }
+}
+
+// everything games get to know about the mouse and keyboard, read once per frame
+#[derive(Clone, Debug, PartialEq)]
+pub struct InputState {
+    pub cursor: Vector2,
+    // cursor movement since the last frame
+    pub cursor_delta: Vector2,
+    keys: [ButtonState; KEYS.len()],
+    buttons: [ButtonState; BUTTONS.len()],
+    // wheel movement this frame, positive is away from the user
+    pub scroll: f32,
+}
+
+impl InputState {
+    pub fn new() -> InputState {
+        InputState {
+            cursor: Vector2::zero(),
+            cursor_delta: Vector2::zero(),
+            keys: [ButtonState::default(); KEYS.len()],
+            buttons: [ButtonState::default(); BUTTONS.len()],
+            scroll: 0.0,
+        }
+    }
+
+    // state for the next frame, edges are found by comparing with this one
+    pub fn next(&self, platform: &dyn Platform, scroll: f32) -> InputState {
+        let cursor = platform.mouse_pos();
+        let mut keys = self.keys;
+        for (state, key) in keys.iter_mut().zip(KEYS) {
+            *state = state.next(platform.key_down(key));
+        }
+        let mut buttons = self.buttons;
+        for (state, button) in buttons.iter_mut().zip(BUTTONS) {
+            *state = state.next(platform.button_down(button));
+        }
+        InputState {
+            cursor,
+            cursor_delta: Vector2::new(cursor.x - self.cursor.x, cursor.y - self.cursor.y),
+            keys,
+            buttons,
+            scroll,
+        }
+    }
+
+    pub fn key(&self, key: Key) -> ButtonState {
+        self.keys[key as usize]
+    }
+
+    pub fn button(&self, button: Button) -> ButtonState {
+        self.buttons[button as usize]
+    }
+
+    // true if any key or button went down this frame
+    pub fn any_pressed(&self) -> bool {
+        self.keys.iter().chain(&self.buttons).any(|state| state.pressed)
+    }
+
+    // adds the presses, releases and scroll of a frame no logic step saw,
+    // so a click between two fixed steps still counts
+    pub fn carry_edges(&mut self, missed: &InputState) {
+        let missed_states = missed.keys.iter().chain(&missed.buttons);
+        for (state, old) in self.keys.iter_mut().chain(self.buttons.iter_mut()).zip(missed_states) {
+            state.pressed |= old.pressed;
+            state.released |= old.released;
+        }
+        self.scroll += missed.scroll;
+    }
+
+    // the same state with the edges used up, for further steps in the same frame
+    pub fn without_edges(&self) -> InputState {
+        let mut input = self.clone();
+        for state in input.keys.iter_mut().chain(input.buttons.iter_mut()) {
+            state.pressed = false;
+            state.released = false;
+        }
+        input.scroll = 0.0;
+        input
+    }
+}
@@ -1,54 +1,293 @@
-use crate::effects::{screen_flash::Flash};
-use rand::prelude::*;
-use raylib::prelude::RaylibDrawHandle;
-use std::time::Duration;
+use crate::canvas::{Canvas, Recorder};
+use crate::config::Config;
+use crate::effects::cursor::{Cursor, Sabotage};
+use crate::effects::desktop::{Desktop, Transform};
+use crate::effects::{screen_flash::Flash, shake::Shake};
+use crate::game_handler::GameOutcome;
+use crate::platform::Platform;
+use crate::safety::{Guard, Limits};
+use crate::selection::{SelectionMode, Selector};
+use rand::rngs::StdRng;
+use raylib::prelude::*;
+
+// enabled effects and the selector that picks between them, in safe mode every
+// effect that flashes or moves gets the gentle dimming in its place
+fn build_effects(config: &Config, screen_size: (i32, i32)) -> (Vec<Box<dyn Effect>>, Selector) {
+    let mut effects: Vec<Box<dyn Effect>> = vec![];
+    let mut weights: Vec<(String, f32)> = vec![];
+    if config.effects.flash.enabled {
+        effects.push(Box::new(Flash::new(screen_size, &config.effects.flash)));
+        weights.push(("Flash".to_string(), config.effects.flash.weight));
+    }
+    if config.effects.shake.enabled {
+        effects.push(Box::new(Shake::new(screen_size, &config.effects.shake)));
+        weights.push(("Shake".to_string(), config.effects.shake.weight));
+    }
+    for (name, transform, effect) in [
+        ("Melt", Transform::Melt, &config.effects.melt),
+        ("Pixelate", Transform::Pixelate, &config.effects.pixelate),
+        ("Flip", Transform::Flip, &config.effects.flip),
+        ("Negative", Transform::Negative, &config.effects.negative),
+        ("Jigsaw", Transform::Jigsaw, &config.effects.jigsaw),
+    ] {
+        if effect.enabled {
+            effects.push(Box::new(Desktop::new(transform, screen_size, effect)));
+            weights.push((name.to_string(), effect.weight));
+        }
+    }
+    for (name, sabotage, effect) in [
+        ("Invert", Sabotage::Invert, &config.effects.invert),
+        ("Drift", Sabotage::Drift, &config.effects.drift),
+        ("Lag", Sabotage::Lag, &config.effects.lag),
+    ] {
+        if effect.enabled {
+            effects.push(Box::new(Cursor::new(sabotage, screen_size, effect)));
+            weights.push((name.to_string(), effect.weight));
+        }
+    }
+    if config.effects.dim.enabled {
+        effects.push(Box::new(Flash::dim(screen_size, &config.effects.dim)));
+        weights.push(("Dim".to_string(), config.effects.dim.weight));
+    }
+    if config.settings.safe_mode {
+        effects = effects
+            .into_iter()
+            .map(|effect| match effect.is_gentle() {
+                true => effect,
+                false => Box::new(Flash::dim(screen_size, &config.effects.dim)) as Box<dyn Effect>,
+            })
+            .collect();
+    }
+    let selector = Selector::new(SelectionMode::Random, &weights, &[]);
+    (effects, selector)
+}
 
 pub struct LossHandler {
     effect_list: Vec<Box<dyn Effect>>,
+    selector: Selector,
     selected: Option<usize>,
-    finished: bool
+    finished: bool,
+    rng: StdRng,
+    // effects draw here first, the guard decides what reaches the screen
+    recorder: Recorder,
+    guard: Guard,
+    // the effect is over and the guard is bringing the screen back
+    fading: bool,
+    // overlay window in desktop coordinates
+    window: Rectangle,
+    // reloaded settings waiting for the running effect to end
+    pending: Option<Config>,
 }
 
 impl LossHandler {
-    pub fn new(screen_size: (i32, i32)) -> LossHandler {
+    pub fn new(config: &Config, window: Rectangle, rng: StdRng) -> LossHandler {
+        let screen_size = (window.width as i32, window.height as i32);
+        let (effect_list, selector) = build_effects(config, screen_size);
         LossHandler {
-            effect_list: vec![Box::new(Flash::new(screen_size, Duration::from_secs(5)))],
+            effect_list,
+            selector,
             selected: None,
             finished: true,
+            rng,
+            recorder: Recorder::new(),
+            guard: Guard::new(screen_size, Limits::new(config.settings.safe_mode)),
+            fading: false,
+            window,
+            pending: None,
+        }
+    }
+
+    // effects are rebuilt from reloaded settings once no effect is running
+    pub fn reload(&mut self, config: Config) {
+        self.pending = Some(config);
+        if self.finished {
+            self.apply_pending();
+        }
+    }
+
+    fn apply_pending(&mut self) {
+        if let Some(config) = self.pending.take() {
+            let screen_size = (self.window.width as i32, self.window.height as i32);
+            (self.effect_list, self.selector) = build_effects(&config, screen_size);
+            self.guard = Guard::new(screen_size, Limits::new(config.settings.safe_mode));
+            self.select_effect();
         }
     }
 
     pub fn select_effect(&mut self) {
-        let mut rng = rand::rng();
-        let num = rng.random_range(0..self.effect_list.len());
+        // with every effect disabled a loss only ends the round
+        if self.effect_list.is_empty() {
+            self.selected = None;
+            return;
+        }
+        let num = self.selector.next(&mut self.rng);
         self.selected = Some(num);
         println!("selected effect: {num}");
     }
 
-    pub fn do_effect(&mut self, d: &mut RaylibDrawHandle, delta_time: f32) {
+    // prepares the selected effect for a lost round, worse losses get a harsher effect
+    pub fn start(&mut self, outcome: &GameOutcome, desktop: &dyn Platform) {
+        if let Some(index) = self.selected {
+            let severity = outcome.severity();
+            println!("loss severity: {severity:.2}");
+            self.effect_list[index].on_start(&mut EffectContext {
+                severity,
+                rng: &mut self.rng,
+                desktop,
+                window: self.window,
+            });
+        }
+    }
+
+    pub fn do_effect(&mut self, d: &mut dyn Canvas, delta_time: f32, desktop: &dyn Platform) {
         if let Some(index) = self.selected {
             self.finished = false;
-            let effect = &mut self.effect_list[index];
-            effect.draw(d, delta_time);
+            if !self.fading {
+                let effect = &mut self.effect_list[index];
+                effect.update(desktop, delta_time);
+                effect.draw(&mut self.recorder, delta_time);
+            }
+            self.guard.show(self.recorder.take(), d, delta_time);
         }
     }
 
-    pub fn finished(&mut self) -> bool {
+    pub fn finished(&mut self, desktop: &dyn Platform) -> bool {
         if let Some(index) = self.selected {
-            let effect = &mut self.effect_list[index];
-            let effect_over = effect.is_finished();
-            if effect_over {
-                self.select_effect();
+            if !self.fading && self.effect_list[index].is_finished() {
+                self.effect_list[index].stop(desktop);
+                self.fading = true;
+            }
+            // the effect may not vanish at once either
+            if self.fading && self.guard.is_clear() {
+                self.fading = false;
+                self.guard.reset();
                 self.finished = true;
+                if self.pending.is_some() {
+                    self.apply_pending();
+                } else {
+                    self.select_effect();
+                }
             }
         }
         self.finished
     }
 }
 
+// what an effect gets to know before it is shown
+pub struct EffectContext<'a> {
+    // 0.0 for a narrow loss up to 1.0 for the worst one
+    pub severity: f32,
+    pub rng: &'a mut StdRng,
+    // for effects that work with what is on screen
+    pub desktop: &'a dyn Platform,
+    // overlay window in desktop coordinates
+    pub window: Rectangle,
+}
+
 pub trait Effect {
+    // called before the effect is drawn for a lost round
+    fn on_start(&mut self, ctx: &mut EffectContext);
+    // called every frame before draw, for effects that act on more than the screen
+    fn update(&mut self, _desktop: &dyn Platform, _delta_time: f32) {}
     // draw the effect
-    fn draw(&mut self, d: &mut RaylibDrawHandle, delta_time: f32);
+    fn draw(&mut self, d: &mut dyn Canvas, delta_time: f32);
+    // the effect is over, anything it changed outside the overlay has to be undone here
+    fn stop(&mut self, _desktop: &dyn Platform) {}
+    // true for effects without flashing or movement, the only ones kept in safe mode
+    fn is_gentle(&self) -> bool {
+        false
+    }
     // returns true when effect is finished
     fn is_finished(&mut self) -> bool;
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game_handler::RoundStats;
+    use crate::platform::headless::Headless;
+    use rand::SeedableRng;
+    use std::time::Duration;
+
+    const WINDOW: Rectangle = Rectangle {
+        x: 0.0,
+        y: 0.0,
+        width: 320.0,
+        height: 200.0,
+    };
+
+    // the flash is the only effect, so it is known which one runs
+    fn flash_only() -> Config {
+        let mut config = Config::default();
+        for effect in [
+            &mut config.effects.shake,
+            &mut config.effects.melt,
+            &mut config.effects.pixelate,
+            &mut config.effects.flip,
+            &mut config.effects.negative,
+            &mut config.effects.jigsaw,
+        ] {
+            effect.enabled = false;
+        }
+        config
+    }
+
+    fn no_effects() -> Config {
+        let mut config = flash_only();
+        config.effects.flash.enabled = false;
+        config
+    }
+
+    #[test]
+    fn reload_between_effects_applies_at_once() {
+        let mut handler = LossHandler::new(&flash_only(), WINDOW, StdRng::seed_from_u64(0));
+        handler.select_effect();
+        assert_eq!(handler.selected, Some(0));
+        handler.reload(no_effects());
+        assert!(handler.effect_list.is_empty());
+        assert_eq!(handler.selected, None);
+    }
+
+    #[test]
+    fn reload_waits_for_the_running_effect() {
+        let desktop = Headless::new((320, 200));
+        let mut canvas = Recorder::new();
+        let mut handler = LossHandler::new(&flash_only(), WINDOW, StdRng::seed_from_u64(0));
+        handler.select_effect();
+        let lost = GameOutcome::Lost(RoundStats {
+            score: 0,
+            duration: Duration::from_secs(5),
+            progress: 0.0,
+            metadata: vec![],
+        });
+        handler.start(&lost, &desktop);
+        handler.do_effect(&mut canvas, 1.0 / 60.0, &desktop);
+
+        handler.reload(no_effects());
+        assert_eq!(handler.effect_list.len(), 1);
+
+        // the flash runs out and the guard brings the screen back before the new settings apply
+        let mut frames = 0;
+        while !handler.finished(&desktop) {
+            assert_eq!(handler.effect_list.len(), 1);
+            handler.do_effect(&mut canvas, 1.0 / 60.0, &desktop);
+            canvas.take();
+            frames += 1;
+            assert!(frames < 60 * 60);
+        }
+        assert!(handler.effect_list.is_empty());
+        assert_eq!(handler.selected, None);
+    }
+
+    #[test]
+    fn safe_mode_keeps_only_gentle_effects() {
+        let mut config = Config::default();
+        let (effects, _) = build_effects(&config, (320, 200));
+        assert!(effects.iter().any(|effect| !effect.is_gentle()));
+
+        config.settings.safe_mode = true;
+        config.effects.dim.enabled = true;
+        let (effects, _) = build_effects(&config, (320, 200));
+        assert_eq!(effects.len(), 8);
+        assert!(effects.iter().all(|effect| effect.is_gentle()));
+    }
+}
@@ -1,36 +1,285 @@
 //#![windows_subsystem = "windows"]
 use mki::{Action, InhibitEvent, Keyboard, bind_key};
+use chrono::{Local, NaiveDate};
 use raylib::{ffi::SetConfigFlags, prelude::*};
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
 use std::sync::{Arc, Mutex};
-use winapi::um::winuser::*;
+mod canvas;
+mod config;
+mod cooldown;
+mod difficulty;
 mod effects;
 mod game_handler;
 mod games;
+mod history;
+mod input;
 mod loss_handler;
+mod overlay;
+mod platform;
+mod safety;
+mod schedule;
+mod selection;
+mod session;
+#[cfg(test)]
+mod simulate;
 mod util;
-use game_handler::GameHandler;
-use loss_handler::LossHandler;
+use canvas::Recorder;
+use config::Config;
+use difficulty::Ratings;
+use game_handler::GameOutcome;
+use history::{ExportFormat, History};
+use overlay::Overlay;
+use platform::headless::Headless;
+use session::{FrameInput, Header, Recording, Replay, Session};
 
-fn screen_size() -> (i32, i32) {
-    unsafe {
-        let width = GetSystemMetrics(SM_CXSCREEN);
-        let height = GetSystemMetrics(SM_CYSCREEN);
-        return (width, height - 1);
+// prints win rates, reaction times, streaks and losses per day from the history file
+fn stats(path: Option<String>) -> ExitCode {
+    let path = match path {
+        Some(path) => PathBuf::from(path),
+        None => history::history_path().unwrap(),
+    };
+    let (rounds, skipped) = match History::load(&path) {
+        Ok(loaded) => loaded,
+        Err(e) => {
+            println!("error: could not read {}: {e}", path.display());
+            return ExitCode::FAILURE;
+        }
+    };
+    if skipped > 0 {
+        println!("warning: skipped {skipped} unreadable lines");
     }
+    if rounds.is_empty() {
+        println!("no rounds played yet");
+        return ExitCode::SUCCESS;
+    }
+    print!("{}", history::summarize(&rounds));
+    ExitCode::SUCCESS
 }
 
-fn get_mouse_pos() -> Vector2 {
-    unsafe {
-        let mut point = winapi::shared::windef::POINT { x: 0, y: 0 };
-        winapi::um::winuser::GetCursorPos(&mut point);
-        return Vector2::new(point.x as f32, point.y as f32);
+// value after a flag like --format, None if the flag is missing
+fn flag_value<'a>(args: &'a [String], flag: &str) -> Option<&'a str> {
+    let index = args.iter().position(|arg| arg == flag)?;
+    args.get(index + 1).map(String::as_str)
+}
+
+// writes the history as csv or json, one row per round
+fn export(args: &[String]) -> ExitCode {
+    let Some(format) = ExportFormat::from_name(flag_value(args, "--format").unwrap_or("csv")) else {
+        println!("error: --format must be csv or json");
+        return ExitCode::FAILURE;
+    };
+    let since = match flag_value(args, "--since") {
+        Some(date) => match NaiveDate::parse_from_str(date, "%Y-%m-%d") {
+            Ok(date) => Some(date),
+            Err(_) => {
+                println!("error: --since must be a date like 2025-01-31");
+                return ExitCode::FAILURE;
+            }
+        },
+        None => None,
+    };
+    let path = match flag_value(args, "--history") {
+        Some(path) => PathBuf::from(path),
+        None => history::history_path().unwrap(),
+    };
+    let rounds = match History::load(&path) {
+        Ok((rounds, _)) => rounds,
+        Err(e) => {
+            println!("error: could not read {}: {e}", path.display());
+            return ExitCode::FAILURE;
+        }
+    };
+
+    let result = match flag_value(args, "--output") {
+        Some(output) => std::fs::File::create(output)
+            .and_then(|file| history::export(&rounds, format, since, &mut std::io::BufWriter::new(file))),
+        None => history::export(&rounds, format, since, &mut std::io::stdout().lock()),
+    };
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            println!("error: export failed: {e}");
+            ExitCode::FAILURE
+        }
     }
 }
 
-fn main() {
+// appends a frame and the outcome it ended with to the recording, a failed write stops recording
+fn record(recording: &mut Option<Recording>, frame: Option<&FrameInput>, outcome: Option<&GameOutcome>) {
+    let Some(file) = recording else {
+        return;
+    };
+    let mut result = Ok(());
+    if let Some(frame) = frame {
+        result = file.frame(frame);
+    }
+    if let (Ok(()), Some(outcome)) = (&result, outcome) {
+        result = file.outcome(outcome);
+    }
+    if let Err(e) = result {
+        println!("error: recording stopped: {e}");
+        *recording = None;
+    }
+}
+
+// plays a recording again through the same game and loss handling and checks
+// that every game ends the way it did
+fn replay(path: &str, windowed: bool) -> ExitCode {
+    let replay = match Replay::load(Path::new(path)) {
+        Ok(replay) => replay,
+        Err(e) => {
+            println!("error: could not load {path}: {e}");
+            return ExitCode::FAILURE;
+        }
+    };
+    let mut session = match replay.session() {
+        Ok(session) => session,
+        Err(e) => {
+            println!("error: could not set up the recorded session:\n{e}");
+            return ExitCode::FAILURE;
+        }
+    };
+    println!("replaying {} frames with seed {}", replay.frames.len(), replay.header.seed);
+    // the recorded desktop is gone, effects that need it get a stand in picture.
+    // every frame puts its cursor there first, cursor effects steer it from where it was recorded
+    let mut desktop = Headless::new(replay.header.screen_size);
+
+    let mut outcomes = vec![];
+    if windowed {
+        let (width, height) = session.window_size();
+        let (mut rl, thread) = raylib::init().title("Replay").size(width, height).build();
+        rl.set_target_fps(60);
+        for frame in &replay.frames {
+            if rl.window_should_close() {
+                println!("replay stopped early");
+                return ExitCode::FAILURE;
+            }
+            let mut d = rl.begin_drawing(&thread);
+            d.clear_background(Color::GRAY);
+            frame.apply(&mut desktop);
+            outcomes.extend(session.frame(frame, &desktop, &mut d));
+        }
+    } else {
+        let mut canvas = Recorder::new();
+        for frame in &replay.frames {
+            frame.apply(&mut desktop);
+            outcomes.extend(session.frame(frame, &desktop, &mut canvas));
+            canvas.take();
+        }
+    }
+    outcomes.extend(session.abort());
+
+    for outcome in &outcomes {
+        println!("{outcome}");
+    }
+    match replay.verify(&outcomes) {
+        Ok(()) => {
+            println!("replay matches the recording: {} games", outcomes.len());
+            ExitCode::SUCCESS
+        }
+        Err(e) => {
+            println!("replay differs from the recording: {e}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+// loads settings.yaml the same way the overlay does and prints every problem,
+// without opening a window
+fn check_config(path: Option<String>) -> ExitCode {
+    let path = match path {
+        Some(path) => PathBuf::from(path),
+        None => config::settings_path().unwrap(),
+    };
+    let contents = match std::fs::read_to_string(&path) {
+        Ok(contents) => contents,
+        Err(e) => {
+            println!("error: could not read {}: {e}", path.display());
+            return ExitCode::FAILURE;
+        }
+    };
+
+    let (_, report) = Config::check(&contents);
+    print!("{report}");
+    println!(
+        "{}: {} errors, {} warnings",
+        path.display(),
+        report.errors.len(),
+        report.warnings.len()
+    );
+    if report.is_ok() {
+        ExitCode::SUCCESS
+    } else {
+        ExitCode::FAILURE
+    }
+}
+
+fn main() -> ExitCode {
+    let args: Vec<String> = std::env::args().collect();
+    if let Some(index) = args.iter().position(|arg| arg == "--check-config") {
+        return check_config(args.get(index + 1).cloned());
+    }
+    if args.get(1).is_some_and(|arg| arg == "stats") {
+        return stats(args.get(2).cloned());
+    }
+    if args.get(1).is_some_and(|arg| arg == "export") {
+        return export(&args[2..]);
+    }
+    if let Some(index) = args.iter().position(|arg| arg == "--replay") {
+        let Some(path) = args.get(index + 1) else {
+            println!("error: --replay needs a file name");
+            return ExitCode::FAILURE;
+        };
+        return replay(path, args.iter().any(|arg| arg == "--window"));
+    }
+
+    // whatever happens, a panic must not leave an effect steering the cursor
+    let default_hook = std::panic::take_hook();
+    std::panic::set_hook(Box::new(move |info| {
+        effects::cursor::release();
+        default_hook(info);
+    }));
+
     let exit_window = Arc::new(Mutex::new(false));
     let exit_window_clone = exit_window.clone();
-    let size_tuple = screen_size();
+    let platform = match platform::native() {
+        Ok(platform) => platform,
+        Err(e) => {
+            println!("error: {e}");
+            return ExitCode::FAILURE;
+        }
+    };
+    let settings_path = config::settings_path().unwrap();
+    let config = match Config::load(&settings_path) {
+        Ok(config) => config,
+        Err(e) => {
+            println!("could not load {}:", settings_path.display());
+            println!("{}", e.to_string().trim_end());
+            return ExitCode::FAILURE;
+        }
+    };
+    let seed = match args.iter().position(|arg| arg == "--seed") {
+        Some(index) => match args.get(index + 1).and_then(|seed| seed.parse::<u64>().ok()) {
+            Some(seed) => seed,
+            None => {
+                println!("error: --seed needs a whole number");
+                return ExitCode::FAILURE;
+            }
+        },
+        None => config.settings.seed.unwrap_or_else(rand::random),
+    };
+    // printed so a session can be played again with --seed
+    println!("seed: {seed}");
+    let overlay = match Overlay::new(platform.as_ref(), config.settings.monitor) {
+        Ok(overlay) => overlay,
+        Err(e) => {
+            println!("error: {e}");
+            return ExitCode::FAILURE;
+        }
+    };
+    let size_tuple = overlay.window_size();
+    let window_pos = overlay.window_pos();
 
     // All ConfigFlags:
     // Basic window flags (1-16)
@@ -57,7 +306,11 @@ fn main() {
     // FLAG_BORDERLESS_WINDOWED_MODE = 32_768 - Set to borderless windowed mode
     // FLAG_INTERLACED_HINT = 65_536        - Set to try enabling interlaced video format
 
-    let flags: u32 = 32_768 + 16_384 + 4_096 + 64 + 16 + 8;
+    let mut flags: u32 = 32_768 + 16_384 + 4_096 + 64 + 16 + 8;
+    if overlay.monitor_count() > 1 {
+        // borderless windowed mode snaps the window to a single monitor
+        flags -= 32_768;
+    }
     unsafe {
         SetConfigFlags(flags);
     }
@@ -65,12 +318,15 @@ fn main() {
         .title("Borderless Fullscreen")
         .size(size_tuple.0, size_tuple.1)
         .build();
+    rl.set_window_position(window_pos.0, window_pos.1);
 
     bind_key(
         Keyboard::F8,
         Action {
             callback: Box::new(move |_, _| {
                 println!("quitting");
+                // runs on the hook thread, the mouse is free even if the main loop is stuck
+                effects::cursor::release();
                 let mut exit = exit_window_clone.lock().unwrap();
                 *exit = true;
             }),
@@ -82,15 +338,50 @@ fn main() {
 
     rl.set_exit_key(Some(KeyboardKey::KEY_F8));
 
-    let mut game_handler = GameHandler::new(size_tuple).unwrap();
-    let mut loss_handler = LossHandler::new(size_tuple);
+    let ratings = Ratings::load(difficulty::difficulty_path().unwrap());
+    let difficulty = ratings.to_json();
+    let mut session = match Session::new(&config, overlay, seed, ratings, Local::now()) {
+        Ok(session) => session,
+        Err(e) => {
+            println!("error: {e}");
+            return ExitCode::FAILURE;
+        }
+    };
+    let mut recording = match args.iter().position(|arg| arg == "--record") {
+        Some(index) => {
+            let Some(path) = args.get(index + 1) else {
+                println!("error: --record needs a file name");
+                return ExitCode::FAILURE;
+            };
+            let header = Header {
+                seed,
+                screen_size: platform.screen_size(),
+                monitors: platform.monitors(),
+                settings: std::fs::read_to_string(&settings_path).unwrap_or_default(),
+                difficulty,
+            };
+            match Recording::create(Path::new(path), &header) {
+                Ok(recording) => {
+                    // a replay only knows the settings the recording started with
+                    println!("recording to {path}, settings changes are ignored until restart");
+                    Some(recording)
+                }
+                Err(e) => {
+                    println!("error: could not create {path}: {e}");
+                    return ExitCode::FAILURE;
+                }
+            }
+        }
+        None => None,
+    };
+    if recording.is_none() {
+        session.watch(settings_path);
+    }
+    session.keep_history(History::new(history::history_path().unwrap()));
 
-    game_handler.select_game();
-    loss_handler.select_effect();
     println!("entering loop");
     while !*exit_window.lock().unwrap() {
-        let mouse_pos = get_mouse_pos();
-        let delta_time = rl.get_frame_time();
+        let frame = FrameInput::capture(platform.as_ref(), rl.get_frame_time(), rl.get_mouse_wheel_move());
 
         let mut d = rl.begin_drawing(&thread);
         d.clear_background(Color {
@@ -99,17 +390,16 @@ fn main() {
             b: 0,
             a: 0,
         });
-
-        if !loss_handler.finished() {
-            loss_handler.do_effect(&mut d, delta_time);
-            continue;
-        }
-        if let Some(result) = game_handler.finished() {
-            if result {
-                loss_handler.do_effect(&mut d, delta_time);
-            }
-        } else if game_handler.ready() {
-            game_handler.do_frame(delta_time, mouse_pos, &mut d);
-        }
+        let outcome = session.frame(&frame, platform.as_ref(), &mut d);
+        record(&mut recording, Some(&frame), outcome.as_ref());
+    }
+    effects::cursor::release();
+    let outcome = session.abort();
+    record(&mut recording, None, outcome.as_ref());
+    if let Some(recording) = recording
+        && let Err(e) = recording.finish()
+    {
+        println!("error: recording could not be saved: {e}");
     }
+    ExitCode::SUCCESS
 }
@@ -0,0 +1,124 @@
+use crate::platform::Platform;
+use rand::prelude::*;
+use raylib::prelude::*;
+use yaml_rust2::Yaml;
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum MonitorMode {
+    // only the primary monitor
+    Primary,
+    // one monitor picked by index, 0 is the primary
+    Index(usize),
+    // one window spanning every monitor, games use the whole area
+    All,
+    // one window spanning every monitor, each game gets a random monitor
+    Random,
+}
+
+impl MonitorMode {
+    pub fn from_yaml(value: &Yaml) -> Option<MonitorMode> {
+        if let Some(index) = value.as_i64() {
+            return usize::try_from(index).ok().map(MonitorMode::Index);
+        }
+        match value.as_str()? {
+            "primary" => Some(MonitorMode::Primary),
+            "all" => Some(MonitorMode::All),
+            "random" => Some(MonitorMode::Random),
+            _ => None,
+        }
+    }
+}
+
+pub struct Overlay {
+    // window rectangle in desktop coordinates
+    window: Rectangle,
+    // covered monitors relative to the window
+    monitors: Vec<Rectangle>,
+    mode: MonitorMode,
+}
+
+fn bounding_box(monitors: &[Rectangle]) -> Rectangle {
+    let min_x = monitors.iter().map(|m| m.x).fold(f32::MAX, f32::min);
+    let min_y = monitors.iter().map(|m| m.y).fold(f32::MAX, f32::min);
+    let max_x = monitors.iter().map(|m| m.x + m.width).fold(f32::MIN, f32::max);
+    let max_y = monitors.iter().map(|m| m.y + m.height).fold(f32::MIN, f32::max);
+    Rectangle::new(min_x, min_y, max_x - min_x, max_y - min_y)
+}
+
+fn clip(area: Rectangle, to: Rectangle) -> Rectangle {
+    let x = area.x.max(to.x);
+    let y = area.y.max(to.y);
+    let right = (area.x + area.width).min(to.x + to.width);
+    let bottom = (area.y + area.height).min(to.y + to.height);
+    Rectangle::new(x, y, (right - x).max(0.0), (bottom - y).max(0.0))
+}
+
+impl Overlay {
+    pub fn new(
+        platform: &dyn Platform,
+        mode: MonitorMode,
+    ) -> Result<Overlay, Box<dyn std::error::Error>> {
+        let mut monitors = platform.monitors();
+        if monitors.is_empty() {
+            let size = platform.screen_size();
+            monitors.push(rrect(0, 0, size.0, size.1));
+        }
+
+        let covered = match mode {
+            MonitorMode::Primary => vec![monitors[0]],
+            MonitorMode::Index(index) => vec![*monitors.get(index).ok_or(format!(
+                "monitor {index} not found, {} connected",
+                monitors.len()
+            ))?],
+            MonitorMode::All | MonitorMode::Random => monitors,
+        };
+
+        // sized as the platform reports, the Windows backend keeps monitors a pixel short against fullscreen
+        let window = bounding_box(&covered);
+
+        let monitors = covered
+            .iter()
+            .map(|m| Rectangle::new(m.x - window.x, m.y - window.y, m.width, m.height))
+            .collect();
+
+        Ok(Overlay {
+            window,
+            monitors,
+            mode,
+        })
+    }
+
+    pub fn window_pos(&self) -> (i32, i32) {
+        (self.window.x as i32, self.window.y as i32)
+    }
+
+    pub fn window_size(&self) -> (i32, i32) {
+        (self.window.width as i32, self.window.height as i32)
+    }
+
+    pub fn mode(&self) -> MonitorMode {
+        self.mode
+    }
+
+    pub fn monitor_count(&self) -> usize {
+        self.monitors.len()
+    }
+
+    // desktop coordinates to window coordinates
+    pub fn to_window(&self, pos: Vector2) -> Vector2 {
+        Vector2::new(pos.x - self.window.x, pos.y - self.window.y)
+    }
+
+    // area the next game should be played in, relative to the window
+    pub fn game_area(&self, rng: &mut impl Rng) -> Rectangle {
+        let window = rrect(0, 0, self.window.width, self.window.height);
+        match self.mode {
+            MonitorMode::All => window,
+            MonitorMode::Random => clip(
+                self.monitors[rng.random_range(0..self.monitors.len())],
+                window,
+            ),
+            MonitorMode::Primary | MonitorMode::Index(_) => clip(self.monitors[0], window),
+        }
+    }
+}
@@ -0,0 +1,113 @@
+use super::{Button, Key, Platform, Screenshot};
+use raylib::prelude::{Rectangle, Vector2};
+use std::cell::Cell;
+use std::time::Duration;
+
+// platform without a desktop, the caller decides what the cursor and keys are doing
+pub struct Headless {
+    screen_size: (i32, i32),
+    monitors: Vec<Rectangle>,
+    // a cell so warp_cursor works like on a real desktop
+    mouse_pos: Cell<Vector2>,
+    keys: Vec<Key>,
+    buttons: Vec<Button>,
+    idle_time: Duration,
+    // what capture returns, a checkerboard when unset
+    screen: Option<Screenshot>,
+}
+
+// gray squares, something visibly moving for effects that use the desktop
+fn checkerboard(width: i32, height: i32) -> Screenshot {
+    let mut pixels = Vec::with_capacity((width.max(0) * height.max(0) * 4) as usize);
+    for y in 0..height.max(0) {
+        for x in 0..width.max(0) {
+            let shade = if (x / 32 + y / 32) % 2 == 0 { 90 } else { 160 };
+            pixels.extend([shade, shade, shade, 255]);
+        }
+    }
+    Screenshot::new(width.max(0), height.max(0), pixels)
+}
+
+impl Headless {
+    pub fn new(screen_size: (i32, i32)) -> Headless {
+        Headless {
+            screen_size,
+            monitors: vec![Rectangle::new(0.0, 0.0, screen_size.0 as f32, screen_size.1 as f32)],
+            mouse_pos: Cell::new(Vector2::new((screen_size.0 / 2) as f32, (screen_size.1 / 2) as f32)),
+            keys: vec![],
+            buttons: vec![],
+            idle_time: Duration::ZERO,
+            screen: None,
+        }
+    }
+
+    // returned as it is by capture, whatever area is asked for
+    #[cfg(test)]
+    pub fn set_screen(&mut self, screen: Screenshot) {
+        self.screen = Some(screen);
+    }
+
+    // first monitor is treated as the primary one
+    pub fn set_monitors(&mut self, monitors: Vec<Rectangle>) {
+        self.monitors = monitors;
+    }
+
+    pub fn set_mouse_pos(&mut self, pos: Vector2) {
+        self.mouse_pos.set(pos);
+    }
+
+    pub fn set_idle_time(&mut self, idle_time: Duration) {
+        self.idle_time = idle_time;
+    }
+
+    pub fn set_key(&mut self, key: Key, down: bool) {
+        self.keys.retain(|k| *k != key);
+        if down {
+            self.keys.push(key);
+        }
+    }
+
+    pub fn set_button(&mut self, button: Button, down: bool) {
+        self.buttons.retain(|b| *b != button);
+        if down {
+            self.buttons.push(button);
+        }
+    }
+}
+
+impl Platform for Headless {
+    fn screen_size(&self) -> (i32, i32) {
+        self.screen_size
+    }
+
+    fn monitors(&self) -> Vec<Rectangle> {
+        self.monitors.clone()
+    }
+
+    fn mouse_pos(&self) -> Vector2 {
+        self.mouse_pos.get()
+    }
+
+    fn key_down(&self, key: Key) -> bool {
+        self.keys.contains(&key)
+    }
+
+    fn button_down(&self, button: Button) -> bool {
+        self.buttons.contains(&button)
+    }
+
+    fn idle_time(&self) -> Duration {
+        self.idle_time
+    }
+
+    fn capture(&self, area: Rectangle) -> Option<Screenshot> {
+        match &self.screen {
+            Some(screen) => Some(screen.clone()),
+            None => Some(checkerboard(area.width as i32, area.height as i32)),
+        }
+    }
+
+    fn warp_cursor(&self, pos: Vector2) {
+        self.mouse_pos.set(pos);
+    }
+}
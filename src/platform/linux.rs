@@ -0,0 +1,239 @@
+use super::{Button, Key, Platform, Screenshot};
+use raylib::prelude::{Rectangle, Vector2};
+use std::os::raw::{c_char, c_int, c_uint, c_ulong};
+use std::ptr;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+use x11::{keysym, xlib, xrandr, xss};
+
+// set by capture_error while a capture is running
+static CAPTURE_FAILED: AtomicBool = AtomicBool::new(false);
+
+// the default handler exits the process on any error, a failed capture should only fail the effect
+unsafe extern "C" fn capture_error(_display: *mut xlib::Display, _event: *mut xlib::XErrorEvent) -> c_int {
+    CAPTURE_FAILED.store(true, Ordering::Relaxed);
+    0
+}
+
+pub struct X11 {
+    display: *mut xlib::Display,
+}
+
+fn key_sym(key: Key) -> c_uint {
+    match key {
+        Key::Up => keysym::XK_Up,
+        Key::Down => keysym::XK_Down,
+        Key::Left => keysym::XK_Left,
+        Key::Right => keysym::XK_Right,
+        Key::Space => keysym::XK_space,
+        Key::Escape => keysym::XK_Escape,
+        Key::F8 => keysym::XK_F8,
+    }
+}
+
+fn button_mask(button: Button) -> c_uint {
+    match button {
+        Button::Left => xlib::Button1Mask,
+        Button::Middle => xlib::Button2Mask,
+        Button::Right => xlib::Button3Mask,
+    }
+}
+
+// one color channel of a pixel scaled to 0..=255, the masks say where it sits
+fn channel(pixel: c_ulong, mask: c_ulong) -> u8 {
+    if mask == 0 {
+        return 0;
+    }
+    let shift = mask.trailing_zeros();
+    let max = mask >> shift;
+    (((pixel & mask) >> shift) * 255 / max) as u8
+}
+
+impl X11 {
+    pub fn open() -> Result<X11, Box<dyn std::error::Error>> {
+        let display = unsafe { xlib::XOpenDisplay(ptr::null()) };
+        if display.is_null() {
+            return Err("could not open X display, is DISPLAY set?".into());
+        }
+        Ok(X11 { display })
+    }
+
+    // returns (root x, root y, button mask) of the pointer
+    fn query_pointer(&self) -> (c_int, c_int, c_uint) {
+        let mut root: xlib::Window = 0;
+        let mut child: xlib::Window = 0;
+        let (mut root_x, mut root_y, mut win_x, mut win_y) = (0, 0, 0, 0);
+        let mut mask: c_uint = 0;
+        unsafe {
+            xlib::XQueryPointer(
+                self.display,
+                xlib::XDefaultRootWindow(self.display),
+                &mut root,
+                &mut child,
+                &mut root_x,
+                &mut root_y,
+                &mut win_x,
+                &mut win_y,
+                &mut mask,
+            );
+        }
+        (root_x, root_y, mask)
+    }
+}
+
+impl Drop for X11 {
+    fn drop(&mut self) {
+        unsafe {
+            xlib::XCloseDisplay(self.display);
+        }
+    }
+}
+
+impl Platform for X11 {
+    fn screen_size(&self) -> (i32, i32) {
+        unsafe {
+            let screen = xlib::XDefaultScreen(self.display);
+            let width = xlib::XDisplayWidth(self.display, screen);
+            let height = xlib::XDisplayHeight(self.display, screen);
+            (width, height)
+        }
+    }
+
+    fn monitors(&self) -> Vec<Rectangle> {
+        let mut monitors: Vec<(bool, Rectangle)> = vec![];
+        unsafe {
+            let mut count: c_int = 0;
+            let info = xrandr::XRRGetMonitors(
+                self.display,
+                xlib::XDefaultRootWindow(self.display),
+                xlib::True,
+                &mut count,
+            );
+            if !info.is_null() {
+                for monitor in std::slice::from_raw_parts(info, count as usize) {
+                    monitors.push((
+                        monitor.primary != 0,
+                        Rectangle::new(
+                            monitor.x as f32,
+                            monitor.y as f32,
+                            monitor.width as f32,
+                            monitor.height as f32,
+                        ),
+                    ));
+                }
+                xrandr::XRRFreeMonitors(info);
+            }
+        }
+        monitors.sort_by_key(|(primary, _)| !*primary);
+        monitors.into_iter().map(|(_, rect)| rect).collect()
+    }
+
+    fn mouse_pos(&self) -> Vector2 {
+        let (x, y, _) = self.query_pointer();
+        Vector2::new(x as f32, y as f32)
+    }
+
+    fn key_down(&self, key: Key) -> bool {
+        // one bit per keycode, 256 keycodes
+        let mut keys: [c_char; 32] = [0; 32];
+        unsafe {
+            xlib::XQueryKeymap(self.display, keys.as_mut_ptr());
+            let code = xlib::XKeysymToKeycode(self.display, key_sym(key) as c_ulong) as usize;
+            code != 0 && (keys[code / 8] as u8 & (1 << (code % 8))) != 0
+        }
+    }
+
+    fn button_down(&self, button: Button) -> bool {
+        let (_, _, mask) = self.query_pointer();
+        (mask & button_mask(button)) != 0
+    }
+
+    fn idle_time(&self) -> Duration {
+        unsafe {
+            let info = xss::XScreenSaverAllocInfo();
+            if info.is_null() {
+                return Duration::ZERO;
+            }
+            let status = xss::XScreenSaverQueryInfo(
+                self.display,
+                xlib::XDefaultRootWindow(self.display),
+                info,
+            );
+            let idle = if status != 0 { (*info).idle as u64 } else { 0 };
+            xlib::XFree(info as *mut _);
+            Duration::from_millis(idle)
+        }
+    }
+
+    fn capture(&self, area: Rectangle) -> Option<Screenshot> {
+        let (width, height) = (area.width as i32, area.height as i32);
+        if width <= 0 || height <= 0 {
+            return None;
+        }
+        unsafe {
+            let root = xlib::XDefaultRootWindow(self.display);
+            let mut attributes: xlib::XWindowAttributes = std::mem::zeroed();
+            if xlib::XGetWindowAttributes(self.display, root, &mut attributes) == 0 {
+                return None;
+            }
+            // XGetImage fails for anything outside the root window, e.g. after the monitor layout changed
+            let (left, top) = (area.x as i32, area.y as i32);
+            let (x0, y0) = (left.max(0), top.max(0));
+            let x1 = (left + width).min(attributes.width);
+            let y1 = (top + height).min(attributes.height);
+            if x1 <= x0 || y1 <= y0 {
+                return None;
+            }
+
+            // the root window holds what is on screen, the overlay included, and may be black under a compositor
+            xlib::XSync(self.display, xlib::False);
+            CAPTURE_FAILED.store(false, Ordering::Relaxed);
+            let previous = xlib::XSetErrorHandler(Some(capture_error));
+            let image = xlib::XGetImage(
+                self.display,
+                root,
+                x0,
+                y0,
+                (x1 - x0) as c_uint,
+                (y1 - y0) as c_uint,
+                xlib::XAllPlanes(),
+                xlib::ZPixmap,
+            );
+            xlib::XSync(self.display, xlib::False);
+            xlib::XSetErrorHandler(previous);
+            if image.is_null() {
+                return None;
+            }
+            if CAPTURE_FAILED.load(Ordering::Relaxed) {
+                xlib::XDestroyImage(image);
+                return None;
+            }
+
+            // the part of area off the root window stays transparent
+            let (red, green, blue) = ((*image).red_mask, (*image).green_mask, (*image).blue_mask);
+            let mut pixels = vec![0u8; (width * height * 4) as usize];
+            for y in y0..y1 {
+                for x in x0..x1 {
+                    let pixel = xlib::XGetPixel(image, x - x0, y - y0);
+                    let at = (((y - top) * width + x - left) * 4) as usize;
+                    pixels[at..at + 4].copy_from_slice(&[
+                        channel(pixel, red),
+                        channel(pixel, green),
+                        channel(pixel, blue),
+                        255,
+                    ]);
+                }
+            }
+            xlib::XDestroyImage(image);
+            Some(Screenshot::new(width, height, pixels))
+        }
+    }
+
+    fn warp_cursor(&self, pos: Vector2) {
+        unsafe {
+            let root = xlib::XDefaultRootWindow(self.display);
+            xlib::XWarpPointer(self.display, 0, root, 0, 0, 0, 0, pos.x as c_int, pos.y as c_int);
+            xlib::XFlush(self.display);
+        }
+    }
+}
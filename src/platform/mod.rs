@@ -0,0 +1,114 @@
+use raylib::prelude::{Rectangle, Vector2};
+use std::fmt;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
+
+pub mod headless;
+#[cfg(all(target_os = "linux", feature = "x11"))]
+mod linux;
+#[cfg(windows)]
+mod windows;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Key {
+    Up,
+    Down,
+    Left,
+    Right,
+    Space,
+    Escape,
+    F8,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Button {
+    Left,
+    Right,
+    Middle,
+}
+
+// numbers screenshots so a texture made from one can be reused
+static NEXT_SCREENSHOT: AtomicU64 = AtomicU64::new(0);
+
+// a picture of part of the desktop, 4 bytes per pixel in RGBA order, rows top to bottom
+#[derive(Clone, PartialEq)]
+pub struct Screenshot {
+    pub width: i32,
+    pub height: i32,
+    pub pixels: Vec<u8>,
+    id: u64,
+    // mean of every pixel, what the picture looks like from far away
+    average: [u8; 4],
+}
+
+impl Screenshot {
+    pub fn new(width: i32, height: i32, pixels: Vec<u8>) -> Screenshot {
+        assert_eq!(pixels.len(), (width.max(0) * height.max(0) * 4) as usize);
+        let mut sum = [0u64; 4];
+        for pixel in pixels.chunks_exact(4) {
+            for (total, channel) in sum.iter_mut().zip(pixel) {
+                *total += *channel as u64;
+            }
+        }
+        let count = (pixels.len() as u64 / 4).max(1);
+        Screenshot {
+            width,
+            height,
+            pixels,
+            id: NEXT_SCREENSHOT.fetch_add(1, Ordering::Relaxed),
+            average: sum.map(|total| (total / count) as u8),
+        }
+    }
+
+    // different for every screenshot, even ones with the same pixels
+    pub fn id(&self) -> u64 {
+        self.id
+    }
+
+    pub fn average(&self) -> [u8; 4] {
+        self.average
+    }
+}
+
+// the pixels would flood every debug print and draw snapshot
+impl fmt::Debug for Screenshot {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Screenshot({}x{})", self.width, self.height)
+    }
+}
+
+pub trait Platform {
+    // size of the primary screen
+    fn screen_size(&self) -> (i32, i32);
+    // every monitor in desktop coordinates, primary first
+    fn monitors(&self) -> Vec<Rectangle>;
+    // cursor position on the desktop, not relative to the window
+    fn mouse_pos(&self) -> Vector2;
+    // true while the key is held, even when the overlay has no focus
+    fn key_down(&self, key: Key) -> bool;
+    // true while the mouse button is held, even when the overlay has no focus
+    fn button_down(&self, button: Button) -> bool;
+    // time since the last keyboard or mouse input anywhere on the desktop
+    fn idle_time(&self) -> Duration;
+    // picture of the desktop inside area (desktop coordinates), None if that is not possible.
+    // Windows leaves the overlay out. X11 reads the root window, which has the overlay in it
+    // without a compositor and may be all black with one, effects drop a black picture
+    fn capture(&self, area: Rectangle) -> Option<Screenshot>;
+    // moves the cursor to pos on the desktop, nothing else about the mouse is changed
+    fn warp_cursor(&self, pos: Vector2);
+}
+
+#[cfg(windows)]
+pub fn native() -> Result<Box<dyn Platform>, Box<dyn std::error::Error>> {
+    Ok(Box::new(windows::Windows))
+}
+
+#[cfg(all(target_os = "linux", feature = "x11"))]
+pub fn native() -> Result<Box<dyn Platform>, Box<dyn std::error::Error>> {
+    Ok(Box::new(linux::X11::open()?))
+}
+
+#[cfg(not(any(windows, all(target_os = "linux", feature = "x11"))))]
+pub fn native() -> Result<Box<dyn Platform>, Box<dyn std::error::Error>> {
+    Err("no platform backend for this target (on linux enable the \"x11\" feature)".into())
+}
@@ -0,0 +1,173 @@
+use super::{Button, Key, Platform, Screenshot};
+use raylib::prelude::{Rectangle, Vector2};
+use std::ptr;
+use std::time::Duration;
+use winapi::shared::minwindef::{BOOL, DWORD, LPARAM, TRUE};
+use winapi::um::sysinfoapi::GetTickCount;
+use winapi::shared::windef::{HDC, HGDIOBJ, HMONITOR, LPRECT, POINT};
+use winapi::um::wingdi::*;
+use winapi::um::winuser::*;
+
+pub struct Windows;
+
+fn virtual_key(key: Key) -> i32 {
+    match key {
+        Key::Up => VK_UP,
+        Key::Down => VK_DOWN,
+        Key::Left => VK_LEFT,
+        Key::Right => VK_RIGHT,
+        Key::Space => VK_SPACE,
+        Key::Escape => VK_ESCAPE,
+        Key::F8 => VK_F8,
+    }
+}
+
+fn virtual_button(button: Button) -> i32 {
+    match button {
+        Button::Left => VK_LBUTTON,
+        Button::Right => VK_RBUTTON,
+        Button::Middle => VK_MBUTTON,
+    }
+}
+
+fn is_down(virtual_key: i32) -> bool {
+    // the high bit is set while the key is held
+    unsafe { (GetAsyncKeyState(virtual_key) as u16 & 0x8000) != 0 }
+}
+
+unsafe extern "system" fn push_monitor(
+    _monitor: HMONITOR,
+    _hdc: HDC,
+    rect: LPRECT,
+    data: LPARAM,
+) -> BOOL {
+    let monitors = unsafe { &mut *(data as *mut Vec<Rectangle>) };
+    let rect = unsafe { *rect };
+    monitors.push(Rectangle::new(
+        rect.left as f32,
+        rect.top as f32,
+        (rect.right - rect.left) as f32,
+        (rect.bottom - rect.top) as f32,
+    ));
+    TRUE
+}
+
+impl Platform for Windows {
+    fn screen_size(&self) -> (i32, i32) {
+        unsafe {
+            let width = GetSystemMetrics(SM_CXSCREEN);
+            let height = GetSystemMetrics(SM_CYSCREEN);
+            // a window covering the whole screen is turned into exclusive fullscreen, which
+            // hides the desktop behind the overlay, one pixel less keeps it a normal window
+            (width, height - 1)
+        }
+    }
+
+    fn monitors(&self) -> Vec<Rectangle> {
+        let mut monitors: Vec<Rectangle> = vec![];
+        unsafe {
+            EnumDisplayMonitors(
+                ptr::null_mut(),
+                ptr::null(),
+                Some(push_monitor),
+                &mut monitors as *mut Vec<Rectangle> as LPARAM,
+            );
+        }
+        // the primary monitor always sits at the desktop origin
+        monitors.sort_by_key(|m| !(m.x == 0.0 && m.y == 0.0));
+        // one pixel short like screen_size, an overlay covering a monitor exactly would go fullscreen
+        for monitor in &mut monitors {
+            monitor.height -= 1.0;
+        }
+        monitors
+    }
+
+    fn mouse_pos(&self) -> Vector2 {
+        unsafe {
+            let mut point = POINT { x: 0, y: 0 };
+            GetCursorPos(&mut point);
+            Vector2::new(point.x as f32, point.y as f32)
+        }
+    }
+
+    fn key_down(&self, key: Key) -> bool {
+        is_down(virtual_key(key))
+    }
+
+    fn button_down(&self, button: Button) -> bool {
+        is_down(virtual_button(button))
+    }
+
+    fn idle_time(&self) -> Duration {
+        unsafe {
+            let mut info = LASTINPUTINFO {
+                cbSize: std::mem::size_of::<LASTINPUTINFO>() as u32,
+                dwTime: 0,
+            };
+            if GetLastInputInfo(&mut info) == 0 {
+                return Duration::ZERO;
+            }
+            // both tick counts wrap after 49 days
+            let idle: DWORD = GetTickCount().wrapping_sub(info.dwTime);
+            Duration::from_millis(idle as u64)
+        }
+    }
+
+    fn capture(&self, area: Rectangle) -> Option<Screenshot> {
+        let (x, y) = (area.x as i32, area.y as i32);
+        let (width, height) = (area.width as i32, area.height as i32);
+        if width <= 0 || height <= 0 {
+            return None;
+        }
+        let mut pixels = vec![0u8; (width * height * 4) as usize];
+        unsafe {
+            let screen = GetDC(ptr::null_mut());
+            if screen.is_null() {
+                return None;
+            }
+            let memory = CreateCompatibleDC(screen);
+            let bitmap = CreateCompatibleBitmap(screen, width, height);
+            let old = SelectObject(memory, bitmap as HGDIOBJ);
+            // without CAPTUREBLT layered windows like the overlay are left out
+            let copied = BitBlt(memory, 0, 0, width, height, screen, x, y, SRCCOPY);
+            SelectObject(memory, old);
+
+            let mut info: BITMAPINFO = std::mem::zeroed();
+            info.bmiHeader.biSize = std::mem::size_of::<BITMAPINFOHEADER>() as u32;
+            info.bmiHeader.biWidth = width;
+            // negative height asks for rows top to bottom
+            info.bmiHeader.biHeight = -height;
+            info.bmiHeader.biPlanes = 1;
+            info.bmiHeader.biBitCount = 32;
+            info.bmiHeader.biCompression = BI_RGB;
+            let lines = GetDIBits(
+                memory,
+                bitmap,
+                0,
+                height as u32,
+                pixels.as_mut_ptr() as *mut _,
+                &mut info,
+                DIB_RGB_COLORS,
+            );
+
+            DeleteObject(bitmap as HGDIOBJ);
+            DeleteDC(memory);
+            ReleaseDC(ptr::null_mut(), screen);
+            if copied == 0 || lines == 0 {
+                return None;
+            }
+        }
+        // BGRA to RGBA, the fourth byte is not set by GDI
+        for pixel in pixels.chunks_exact_mut(4) {
+            pixel.swap(0, 2);
+            pixel[3] = 255;
+        }
+        Some(Screenshot::new(width, height, pixels))
+    }
+
+    fn warp_cursor(&self, pos: Vector2) {
+        unsafe {
+            SetCursorPos(pos.x as i32, pos.y as i32);
+        }
+    }
+}
@@ -0,0 +1,525 @@
+use crate::canvas::{Canvas, DrawCommand};
+use raylib::prelude::*;
+use std::collections::VecDeque;
+
+// the desktop behind the overlay is unknown, a mid gray is assumed so darkening counts as much as brightening
+const BACKGROUND: f32 = 0.5;
+// a change of relative luminance this big counts as half a flash
+const LUMINANCE_FLASH: f32 = 0.1;
+// same for the share of the screen that is saturated red
+const RED_FLASH: f32 = 0.25;
+// how far the output may drift while it is held after too many flashes
+const HOLD: f32 = 0.001;
+
+// how much a frame may differ from the one before it
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Limits {
+    // relative luminance change per second
+    pub luminance_rate: f32,
+    // change of the saturated red share of the screen per second
+    pub red_rate: f32,
+    // flashes allowed in any one second
+    pub max_flashes: usize,
+}
+
+impl Limits {
+    // with these rates no more than two flashes fit in a second, safe mode halves them
+    pub fn new(safe_mode: bool) -> Limits {
+        let scale = if safe_mode { 0.5 } else { 1.0 };
+        Limits {
+            luminance_rate: 0.4 * scale,
+            red_rate: 1.0 * scale,
+            max_flashes: 3,
+        }
+    }
+}
+
+// how a frame looks on average over the whole screen
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Sample {
+    // relative luminance, 0.0 black to 1.0 white
+    pub luminance: f32,
+    // share of the screen that is saturated red
+    pub red: f32,
+}
+
+impl Sample {
+    fn background() -> Sample {
+        Sample {
+            luminance: BACKGROUND,
+            red: 0.0,
+        }
+    }
+}
+
+fn linear(channel: u8) -> f32 {
+    let c = channel as f32 / 255.0;
+    if c <= 0.03928 {
+        c / 12.92
+    } else {
+        ((c + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+// relative luminance as defined by WCAG
+fn luminance(color: [u8; 4]) -> f32 {
+    0.2126 * linear(color[0]) + 0.7152 * linear(color[1]) + 0.0722 * linear(color[2])
+}
+
+fn saturated_red(color: [u8; 4]) -> bool {
+    let sum = color[0] as f32 + color[1] as f32 + color[2] as f32;
+    sum > 0.0 && color[0] as f32 / sum >= 0.8
+}
+
+// screen average of the commands drawn in order over the background, shapes are
+// blended in by the share of the screen they cover, text is too small to count
+pub fn measure(commands: &[DrawCommand], screen_size: (i32, i32)) -> Sample {
+    let screen = (screen_size.0.max(1) * screen_size.1.max(1)) as f32;
+    let clipped = |x: i32, y: i32, width: i32, height: i32| {
+        let w = (x + width).min(screen_size.0) - x.max(0);
+        let h = (y + height).min(screen_size.1) - y.max(0);
+        (w.max(0) * h.max(0)) as f32 / screen
+    };
+    let mut sample = Sample::background();
+    for command in commands {
+        let (cover, color) = match command {
+            DrawCommand::Clear { color } => (1.0, *color),
+            DrawCommand::Rectangle {
+                x,
+                y,
+                width,
+                height,
+                color,
+            } => (clipped(*x, *y, *width, *height), *color),
+            DrawCommand::Circle { radius, color, .. } => {
+                ((std::f32::consts::PI * radius * radius / screen).min(1.0), *color)
+            }
+            // counted as its average color, rotation is ignored
+            DrawCommand::Image {
+                image,
+                x,
+                y,
+                color,
+                ..
+            } => {
+                let average = image.average();
+                let tinted = [0, 1, 2, 3].map(|i| (average[i] as u32 * color[i] as u32 / 255) as u8);
+                (clipped(*x as i32, *y as i32, image.width, image.height), tinted)
+            }
+            DrawCommand::Text { .. } => continue,
+        };
+        let weight = cover * color[3] as f32 / 255.0;
+        let red = if saturated_red(color) { 1.0 } else { 0.0 };
+        sample.luminance += (luminance(color) - sample.luminance) * weight;
+        sample.red += (red - sample.red) * weight;
+    }
+    sample
+}
+
+// counts WCAG style flashes: a flash is a pair of opposite changes of at least threshold
+struct FlashCounter {
+    threshold: f32,
+    // the furthest value since the last counted change
+    anchor: f32,
+    rising: Option<bool>,
+    // times of the counted changes
+    changes: VecDeque<f32>,
+}
+
+impl FlashCounter {
+    fn new(threshold: f32, start: f32) -> FlashCounter {
+        FlashCounter {
+            threshold,
+            anchor: start,
+            rising: None,
+            changes: VecDeque::new(),
+        }
+    }
+
+    fn push(&mut self, time: f32, value: f32) {
+        let change = value - self.anchor;
+        let turned = match self.rising {
+            None => change.abs() >= self.threshold,
+            Some(true) => change <= -self.threshold,
+            Some(false) => change >= self.threshold,
+        };
+        if turned {
+            self.changes.push_back(time);
+            self.rising = Some(change > 0.0);
+            self.anchor = value;
+        } else if self.rising.is_some_and(|rising| rising == (change > 0.0)) {
+            self.anchor = value;
+        }
+        while self.changes.front().is_some_and(|at| time - at > 1.0) {
+            self.changes.pop_front();
+        }
+    }
+
+    // changes within the last second
+    fn recent(&self) -> usize {
+        self.changes.len()
+    }
+}
+
+// keeps what an effect draws within the limits, frames that change too fast are
+// dimmed and the last good frame is held while the output has flashed too often
+pub struct Guard {
+    limits: Limits,
+    screen_size: (i32, i32),
+    time: f32,
+    // the frame on screen, undimmed, and the scale it is drawn at
+    held: Vec<DrawCommand>,
+    held_scale: f32,
+    shown: Sample,
+    luminance: FlashCounter,
+    red: FlashCounter,
+}
+
+// the scales up to 1.0 that keep the frame within step of shown, None if there are none
+fn fit(target: Sample, shown: Sample, step: Sample) -> Option<(f32, f32)> {
+    let mut low: f32 = 0.0;
+    let mut high: f32 = 1.0;
+    let channels = [
+        (target.luminance - BACKGROUND, shown.luminance - BACKGROUND, step.luminance),
+        (target.red, shown.red, step.red),
+    ];
+    for (target, shown, step) in channels {
+        if target.abs() < f32::EPSILON {
+            if shown.abs() > step {
+                return None;
+            }
+            continue;
+        }
+        let (a, b) = ((shown - step) / target, (shown + step) / target);
+        low = low.max(a.min(b));
+        high = high.min(a.max(b));
+    }
+    (low <= high).then_some((low, high))
+}
+
+fn scaled(commands: &[DrawCommand], scale: f32) -> Vec<DrawCommand> {
+    let fade = |color: [u8; 4]| [color[0], color[1], color[2], (color[3] as f32 * scale) as u8];
+    commands
+        .iter()
+        .map(|command| match command.clone() {
+            DrawCommand::Clear { color } => DrawCommand::Clear { color: fade(color) },
+            DrawCommand::Rectangle {
+                x,
+                y,
+                width,
+                height,
+                color,
+            } => DrawCommand::Rectangle {
+                x,
+                y,
+                width,
+                height,
+                color: fade(color),
+            },
+            DrawCommand::Circle { x, y, radius, color } => DrawCommand::Circle {
+                x,
+                y,
+                radius,
+                color: fade(color),
+            },
+            DrawCommand::Text {
+                text,
+                x,
+                y,
+                font_size,
+                color,
+            } => DrawCommand::Text {
+                text,
+                x,
+                y,
+                font_size,
+                color: fade(color),
+            },
+            DrawCommand::Image {
+                image,
+                x,
+                y,
+                rotation,
+                color,
+            } => DrawCommand::Image {
+                image,
+                x,
+                y,
+                rotation,
+                color: fade(color),
+            },
+        })
+        .collect()
+}
+
+fn replay(commands: &[DrawCommand], d: &mut dyn Canvas) {
+    let color = |c: &[u8; 4]| Color::new(c[0], c[1], c[2], c[3]);
+    for command in commands {
+        match command {
+            DrawCommand::Clear { color: c } => d.clear_background(color(c)),
+            DrawCommand::Rectangle {
+                x,
+                y,
+                width,
+                height,
+                color: c,
+            } => d.draw_rectangle(*x, *y, *width, *height, color(c)),
+            DrawCommand::Circle { x, y, radius, color: c } => d.draw_circle(*x, *y, *radius, color(c)),
+            DrawCommand::Text {
+                text,
+                x,
+                y,
+                font_size,
+                color: c,
+            } => d.draw_text(text, *x, *y, *font_size, color(c)),
+            DrawCommand::Image {
+                image,
+                x,
+                y,
+                rotation,
+                color: c,
+            } => d.draw_image(image, *x, *y, *rotation, color(c)),
+        }
+    }
+}
+
+impl Guard {
+    pub fn new(screen_size: (i32, i32), limits: Limits) -> Guard {
+        Guard {
+            limits,
+            screen_size,
+            time: 0.0,
+            held: vec![],
+            held_scale: 0.0,
+            shown: Sample::background(),
+            luminance: FlashCounter::new(LUMINANCE_FLASH, BACKGROUND),
+            red: FlashCounter::new(RED_FLASH, 0.0),
+        }
+    }
+
+    // draws the frame an effect wants to show, as far as the limits allow
+    pub fn show(&mut self, commands: Vec<DrawCommand>, d: &mut dyn Canvas, delta_time: f32) {
+        self.time += delta_time;
+        let flashing = self.luminance.recent().max(self.red.recent()) >= 2 * self.limits.max_flashes;
+        let step = if flashing {
+            Sample {
+                luminance: HOLD,
+                red: HOLD,
+            }
+        } else {
+            Sample {
+                luminance: self.limits.luminance_rate * delta_time,
+                red: self.limits.red_rate * delta_time,
+            }
+        };
+
+        let target = measure(&commands, self.screen_size);
+        if let Some((low, high)) = fit(target, self.shown, step) {
+            self.held = commands;
+            self.held_scale = self.settle(high, low, step);
+        } else {
+            // the new frame cannot get close enough, fade the old one out as far as allowed instead
+            self.held_scale = self.fade_held(step);
+        }
+
+        let output = scaled(&self.held, self.held_scale);
+        self.shown = measure(&output, self.screen_size);
+        self.luminance.push(self.time, self.shown.luminance);
+        self.red.push(self.time, self.shown.red);
+        replay(&output, d);
+    }
+
+    // alpha is rounded down to whole numbers when the held frame is scaled, which can take it
+    // past step, so the scale from preferred towards other whose drawn frame stays within step is used
+    fn settle(&self, preferred: f32, other: f32, step: Sample) -> f32 {
+        let direction = if other < preferred { -1.0 } else { 1.0 };
+        let mut scale = preferred;
+        while (scale - other) * direction <= 0.0 {
+            let drawn = measure(&scaled(&self.held, scale), self.screen_size);
+            if (drawn.luminance - self.shown.luminance).abs() <= step.luminance
+                && (drawn.red - self.shown.red).abs() <= step.red
+            {
+                return scale;
+            }
+            scale += direction / 255.0;
+        }
+        preferred
+    }
+
+    // lowest scale the held frame can drop to without moving more than step. layers blend over
+    // each other, so the drawn frame is not linear in the scale and fit cannot be used here
+    fn fade_held(&self, step: Sample) -> f32 {
+        let mut scale = self.held_scale;
+        while scale > 0.0 {
+            let lower = (scale - 1.0 / 255.0).max(0.0);
+            let drawn = measure(&scaled(&self.held, lower), self.screen_size);
+            if (drawn.luminance - self.shown.luminance).abs() > step.luminance
+                || (drawn.red - self.shown.red).abs() > step.red
+            {
+                break;
+            }
+            scale = lower;
+        }
+        scale
+    }
+
+    // nothing of the last effect is left on screen
+    pub fn is_clear(&self) -> bool {
+        (self.shown.luminance - BACKGROUND).abs() < 0.005 && self.shown.red < 0.005
+    }
+
+    pub fn reset(&mut self) {
+        *self = Guard::new(self.screen_size, self.limits);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::canvas::Recorder;
+    use crate::config::EffectsConfig;
+    use crate::effects::screen_flash::Flash;
+    use crate::loss_handler::{Effect, EffectContext};
+    use crate::platform::headless::Headless;
+    use rand::SeedableRng;
+    use rand::rngs::StdRng;
+
+    const SCREEN: (i32, i32) = (320, 200);
+
+    // the worst an effect did over a run of frames
+    #[derive(Copy, Clone, Debug, Default, PartialEq)]
+    struct Analysis {
+        frames: usize,
+        // largest change between two frames, per second
+        luminance_rate: f32,
+        red_rate: f32,
+        // most flashes in any one second
+        flashes: usize,
+    }
+
+    impl Analysis {
+        // a small margin for float rounding
+        fn within(&self, limits: &Limits) -> bool {
+            self.luminance_rate <= limits.luminance_rate * 1.05
+                && self.red_rate <= limits.red_rate * 1.05
+                && self.flashes <= limits.max_flashes
+        }
+    }
+
+    // measures frames that were drawn delta_time apart
+    fn analyze_frames(frames: &[Vec<DrawCommand>], screen_size: (i32, i32), delta_time: f32) -> Analysis {
+        let mut analysis = Analysis {
+            frames: frames.len(),
+            ..Analysis::default()
+        };
+        let mut last = Sample::background();
+        let mut luminance = FlashCounter::new(LUMINANCE_FLASH, BACKGROUND);
+        let mut red = FlashCounter::new(RED_FLASH, 0.0);
+        for (index, frame) in frames.iter().enumerate() {
+            let time = (index + 1) as f32 * delta_time;
+            let sample = measure(frame, screen_size);
+            analysis.luminance_rate = analysis
+                .luminance_rate
+                .max((sample.luminance - last.luminance).abs() / delta_time);
+            analysis.red_rate = analysis.red_rate.max((sample.red - last.red).abs() / delta_time);
+            luminance.push(time, sample.luminance);
+            red.push(time, sample.red);
+            analysis.flashes = analysis.flashes.max(luminance.recent().max(red.recent()) / 2);
+            last = sample;
+        }
+        analysis
+    }
+
+    // draws an effect for a number of frames without a window, guarded or as it is,
+    // so effects can be checked against the limits before they ever reach a screen
+    fn analyze(
+        effect: &mut dyn Effect,
+        ctx: &mut EffectContext,
+        screen_size: (i32, i32),
+        guard: Option<Limits>,
+        delta_time: f32,
+        frames: usize,
+    ) -> Analysis {
+        let mut guard = guard.map(|limits| Guard::new(screen_size, limits));
+        let mut recorder = Recorder::new();
+        let mut output = Recorder::new();
+        let mut drawn = vec![];
+        effect.on_start(ctx);
+        for _ in 0..frames {
+            effect.draw(&mut recorder, delta_time);
+            match &mut guard {
+                Some(guard) => {
+                    guard.show(recorder.take(), &mut output, delta_time);
+                    drawn.push(output.take());
+                }
+                None => drawn.push(recorder.take()),
+            }
+        }
+        analyze_frames(&drawn, screen_size, delta_time)
+    }
+
+    // the default flash at full severity for a second longer than it lasts
+    fn analyze_flash(guard: Option<Limits>) -> Analysis {
+        let desktop = Headless::new(SCREEN);
+        let mut rng = StdRng::seed_from_u64(0);
+        let mut ctx = EffectContext {
+            severity: 1.0,
+            rng: &mut rng,
+            desktop: &desktop,
+            window: Rectangle::new(0.0, 0.0, SCREEN.0 as f32, SCREEN.1 as f32),
+        };
+        let mut flash = Flash::new(SCREEN, &EffectsConfig::default().flash);
+        analyze(&mut flash, &mut ctx, SCREEN, guard, 1.0 / 60.0, 360)
+    }
+
+    #[test]
+    fn guard_fades_out_a_frame_that_ends_at_once() {
+        let flash = vec![DrawCommand::Clear {
+            color: [112, 31, 22, 255],
+        }];
+        // layers blend over each other, so this one does not fade linearly
+        let layered = vec![
+            DrawCommand::Clear { color: [0, 0, 0, 255] },
+            DrawCommand::Rectangle {
+                x: 0,
+                y: 0,
+                width: SCREEN.0 / 2,
+                height: SCREEN.1,
+                color: [200, 200, 200, 255],
+            },
+        ];
+        for frame in [flash, layered] {
+            let mut guard = Guard::new(SCREEN, Limits::new(false));
+            let mut output = Recorder::new();
+            for _ in 0..120 {
+                guard.show(frame.clone(), &mut output, 1.0 / 60.0);
+            }
+            assert!(!guard.is_clear());
+            let mut frames = 0;
+            while !guard.is_clear() {
+                guard.show(vec![], &mut output, 1.0 / 60.0);
+                frames += 1;
+                assert!(frames < 600, "{frame:?} still at {:?}", guard.shown);
+            }
+        }
+    }
+
+    #[test]
+    fn unguarded_flash_is_over_the_limits() {
+        let analysis = analyze_flash(None);
+        assert_eq!(analysis.frames, 360);
+        assert!(analysis.luminance_rate > Limits::new(false).luminance_rate * 10.0);
+        assert!(!analysis.within(&Limits::new(false)));
+    }
+
+    #[test]
+    fn guarded_flash_is_within_the_limits() {
+        for safe_mode in [false, true] {
+            let limits = Limits::new(safe_mode);
+            let analysis = analyze_flash(Some(limits));
+            assert!(analysis.within(&limits), "{analysis:?}");
+            // the guard slows the flash down but still shows it
+            assert!(analysis.luminance_rate > 0.0);
+        }
+    }
+}
@@ -0,0 +1,191 @@
+use chrono::{Datelike, NaiveDateTime, Timelike};
+
+const DAY_NAMES: [&str; 7] = ["mon", "tue", "wed", "thu", "fri", "sat", "sun"];
+
+// a daily time range on some days of the week
+#[derive(Clone, Debug, PartialEq)]
+pub struct Window {
+    // indexed from monday
+    days: [bool; 7],
+    // minutes since midnight, to may be 1440 for the end of the day
+    from: u32,
+    to: u32,
+}
+
+// "10:30" to minutes since midnight, "24:00" is allowed as the end of the day
+pub fn parse_time(text: &str) -> Option<u32> {
+    let (hours, minutes) = text.trim().split_once(':')?;
+    let hours: u32 = hours.parse().ok()?;
+    let minutes: u32 = minutes.parse().ok()?;
+    let total = hours * 60 + minutes;
+    if minutes < 60 && total <= 24 * 60 {
+        Some(total)
+    } else {
+        None
+    }
+}
+
+// "mon", "weekdays", "weekends" or "all" to the days they cover
+pub fn parse_days(name: &str) -> Option<[bool; 7]> {
+    let name = name.trim().to_lowercase();
+    match name.as_str() {
+        "all" | "daily" => Some([true; 7]),
+        "weekdays" => Some([true, true, true, true, true, false, false]),
+        "weekends" => Some([false, false, false, false, false, true, true]),
+        _ => {
+            // accept full names too, "monday" starts with "mon"
+            let index = DAY_NAMES.iter().position(|day| name.starts_with(day))?;
+            let mut days = [false; 7];
+            days[index] = true;
+            Some(days)
+        }
+    }
+}
+
+impl Window {
+    pub fn new(days: [bool; 7], from: u32, to: u32) -> Window {
+        Window {
+            days,
+            from,
+            to,
+        }
+    }
+
+    pub fn contains(&self, time: NaiveDateTime) -> bool {
+        let day = time.weekday().num_days_from_monday() as usize;
+        let minute = time.hour() * 60 + time.minute();
+        if self.from < self.to {
+            self.days[day] && minute >= self.from && minute < self.to
+        } else if self.from > self.to {
+            // runs past midnight, the early part belongs to the day before
+            (self.days[day] && minute >= self.from) || (self.days[(day + 6) % 7] && minute < self.to)
+        } else {
+            self.days[day]
+        }
+    }
+
+    // times the window opens or closes on the day of the given date
+    fn edges(&self, time: NaiveDateTime) -> Vec<NaiveDateTime> {
+        let date = time.date();
+        [self.from, self.to]
+            .iter()
+            .filter_map(|&minutes| {
+                if minutes >= 24 * 60 {
+                    date.succ_opt()?.and_hms_opt(0, 0, 0)
+                } else {
+                    date.and_hms_opt(minutes / 60, minutes % 60, 0)
+                }
+            })
+            .collect()
+    }
+}
+
+// when games may start, nothing configured means any time
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Schedule {
+    pub allow: Vec<Window>,
+    pub deny: Vec<Window>,
+}
+
+impl Schedule {
+    pub fn allows(&self, time: NaiveDateTime) -> bool {
+        let allowed = self.allow.is_empty() || self.allow.iter().any(|w| w.contains(time));
+        allowed && !self.deny.iter().any(|w| w.contains(time))
+    }
+
+    // first moment after time that games may start, None if that is more than a week away
+    pub fn next_open(&self, time: NaiveDateTime) -> Option<NaiveDateTime> {
+        // the answer can only change where a window opens or closes
+        let mut edges: Vec<NaiveDateTime> = vec![];
+        let mut day = time;
+        for _ in 0..8 {
+            for window in self.allow.iter().chain(self.deny.iter()) {
+                edges.extend(window.edges(day));
+            }
+            day = day.checked_add_days(chrono::Days::new(1))?;
+        }
+        edges.sort();
+        edges.into_iter().find(|&edge| edge > time && self.allows(edge))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::NaiveDate;
+
+    // 2025-03-03 is a monday
+    fn at(day: u32, hour: u32, minute: u32) -> NaiveDateTime {
+        NaiveDate::from_ymd_opt(2025, 3, day).unwrap().and_hms_opt(hour, minute, 0).unwrap()
+    }
+
+    fn window(days: &str, from: &str, to: &str) -> Window {
+        Window::new(parse_days(days).unwrap(), parse_time(from).unwrap(), parse_time(to).unwrap())
+    }
+
+    #[test]
+    fn overnight_window_belongs_to_the_day_it_starts() {
+        let night = window("weekdays", "22:00", "02:00");
+        assert!(night.contains(at(7, 23, 0)));
+        // saturday morning is still the friday night
+        assert!(night.contains(at(8, 1, 59)));
+        assert!(!night.contains(at(8, 2, 0)));
+        assert!(!night.contains(at(8, 23, 0)));
+        // monday morning is the sunday night
+        assert!(!night.contains(at(10, 1, 0)));
+        assert!(!night.contains(at(10, 21, 59)));
+        assert!(night.contains(at(10, 22, 0)));
+    }
+
+    #[test]
+    fn days_filter_limits_the_window() {
+        let mut days = parse_days("mon").unwrap();
+        days[2] = true;
+        let window = Window::new(days, parse_time("10:00").unwrap(), parse_time("12:00").unwrap());
+        assert!(window.contains(at(3, 11, 0)));
+        assert!(!window.contains(at(4, 11, 0)));
+        assert!(window.contains(at(5, 10, 0)));
+        assert!(!window.contains(at(5, 12, 0)));
+        assert_eq!(parse_days("Wednesday"), parse_days("wed"));
+        assert_eq!(parse_days("someday"), None);
+    }
+
+    #[test]
+    fn deny_wins_over_allow() {
+        let schedule = Schedule {
+            allow: vec![window("all", "08:00", "24:00")],
+            deny: vec![window("all", "12:00", "13:00")],
+        };
+        assert!(schedule.allows(at(3, 11, 59)));
+        assert!(!schedule.allows(at(3, 12, 30)));
+        assert!(!schedule.allows(at(3, 7, 0)));
+        assert_eq!(schedule.next_open(at(3, 12, 30)), Some(at(3, 13, 0)));
+    }
+
+    #[test]
+    fn next_open_skips_a_closed_weekend() {
+        let schedule = Schedule {
+            allow: vec![window("weekdays", "09:00", "17:00")],
+            deny: vec![],
+        };
+        assert!(!schedule.allows(at(8, 12, 0)));
+        assert_eq!(schedule.next_open(at(8, 12, 0)), Some(at(10, 9, 0)));
+        assert_eq!(schedule.next_open(at(7, 17, 0)), Some(at(10, 9, 0)));
+    }
+
+    #[test]
+    fn next_open_looks_a_full_week_ahead() {
+        // saturday 10:00 has just passed, the next one is seven days out
+        let schedule = Schedule {
+            allow: vec![window("sat", "10:00", "11:00")],
+            deny: vec![],
+        };
+        assert_eq!(schedule.next_open(at(8, 12, 0)), Some(at(15, 10, 0)));
+
+        let never = Schedule {
+            allow: vec![Window::new([false; 7], 600, 660)],
+            deny: vec![],
+        };
+        assert_eq!(never.next_open(at(8, 12, 0)), None);
+    }
+}
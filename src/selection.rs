@@ -0,0 +1,181 @@
+use rand::prelude::*;
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum SelectionMode {
+    // weighted random, repeats allowed
+    Random,
+    // weighted random, never the same game twice in a row
+    NoRepeat,
+    // every game once per cycle in weighted random order
+    Shuffle,
+    // fixed order from the playlist setting
+    Playlist,
+}
+
+impl SelectionMode {
+    pub fn from_name(name: &str) -> Option<SelectionMode> {
+        match name {
+            "random" => Some(SelectionMode::Random),
+            "no_repeat" => Some(SelectionMode::NoRepeat),
+            "shuffle" => Some(SelectionMode::Shuffle),
+            "playlist" => Some(SelectionMode::Playlist),
+            _ => None,
+        }
+    }
+}
+
+pub struct Selector {
+    mode: SelectionMode,
+    weights: Vec<f32>,
+    // playlist as indices into the game list
+    playlist: Vec<usize>,
+    position: usize,
+    // games left in the current shuffle cycle
+    bag: Vec<usize>,
+    last: Option<usize>,
+}
+
+// index into candidates picked by weight, uniform if every weight is zero
+fn weighted_pick(candidates: &[usize], weights: &[f32], rng: &mut impl Rng) -> usize {
+    let total: f32 = candidates.iter().map(|&i| weights[i]).sum();
+    if total <= 0.0 {
+        return candidates[rng.random_range(0..candidates.len())];
+    }
+    let mut roll = rng.random_range(0.0..total);
+    for &i in candidates {
+        if roll < weights[i] {
+            return i;
+        }
+        roll -= weights[i];
+    }
+    // float rounding can leave a tiny remainder
+    *candidates.last().unwrap()
+}
+
+impl Selector {
+    // games are (name, weight) in game list order, names are matched against the playlist
+    pub fn new(mode: SelectionMode, games: &[(String, f32)], playlist: &[String]) -> Selector {
+        let mut order: Vec<usize> = playlist
+            .iter()
+            .filter_map(|name| games.iter().position(|(game, _)| game == name))
+            .collect();
+        if order.is_empty() {
+            order = (0..games.len()).collect();
+        }
+
+        Selector {
+            mode,
+            weights: games.iter().map(|(_, weight)| *weight).collect(),
+            playlist: order,
+            position: 0,
+            bag: vec![],
+            last: None,
+        }
+    }
+
+    // games that can come up at all, zero weight games are skipped unless nothing else is left
+    fn candidates(&self) -> Vec<usize> {
+        let weighted: Vec<usize> = (0..self.weights.len()).filter(|&i| self.weights[i] > 0.0).collect();
+        if weighted.is_empty() {
+            (0..self.weights.len()).collect()
+        } else {
+            weighted
+        }
+    }
+
+    pub fn next(&mut self, rng: &mut impl Rng) -> usize {
+        let index = match self.mode {
+            SelectionMode::Random => weighted_pick(&self.candidates(), &self.weights, rng),
+            SelectionMode::NoRepeat => {
+                let mut candidates = self.candidates();
+                if candidates.len() > 1 {
+                    candidates.retain(|&i| Some(i) != self.last);
+                }
+                weighted_pick(&candidates, &self.weights, rng)
+            }
+            SelectionMode::Shuffle => {
+                if self.bag.is_empty() {
+                    self.bag = self.candidates();
+                }
+                // avoid a repeat across the start of a new cycle
+                let mut options: Vec<usize> = self.bag.iter().copied().filter(|&i| Some(i) != self.last).collect();
+                if options.is_empty() {
+                    options = self.bag.clone();
+                }
+                let index = weighted_pick(&options, &self.weights, rng);
+                self.bag.retain(|&i| i != index);
+                index
+            }
+            SelectionMode::Playlist => {
+                let index = self.playlist[self.position % self.playlist.len()];
+                self.position += 1;
+                index
+            }
+        };
+        self.last = Some(index);
+        index
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::rngs::StdRng;
+
+    fn games() -> Vec<(String, f32)> {
+        vec![
+            ("Pong".to_string(), 1.0),
+            ("Circles".to_string(), 3.0),
+            ("Avoider".to_string(), 0.5),
+        ]
+    }
+
+    fn picks(selector: &mut Selector, seed: u64, count: usize) -> Vec<usize> {
+        let mut rng = StdRng::seed_from_u64(seed);
+        (0..count).map(|_| selector.next(&mut rng)).collect()
+    }
+
+    #[test]
+    fn shuffle_plays_every_game_once_per_cycle() {
+        for seed in 0..20 {
+            let mut selector = Selector::new(SelectionMode::Shuffle, &games(), &[]);
+            let picks = picks(&mut selector, seed, 30);
+            for cycle in picks.chunks(3) {
+                let mut sorted = cycle.to_vec();
+                sorted.sort();
+                assert_eq!(sorted, [0, 1, 2], "seed {seed}: {picks:?}");
+            }
+            for pair in picks.windows(2) {
+                assert_ne!(pair[0], pair[1], "seed {seed}: {picks:?}");
+            }
+        }
+    }
+
+    #[test]
+    fn no_repeat_never_picks_the_same_game_twice_in_a_row() {
+        let mut selector = Selector::new(SelectionMode::NoRepeat, &games(), &[]);
+        let picks = picks(&mut selector, 7, 500);
+        assert!(picks.windows(2).all(|pair| pair[0] != pair[1]));
+        // the weights still count, Circles is the heaviest
+        let circles = picks.iter().filter(|&&i| i == 1).count();
+        assert!(circles > picks.len() / 3, "{circles}");
+    }
+
+    #[test]
+    fn playlist_wraps_in_order() {
+        let playlist = ["Avoider", "Pong", "Tetris", "Avoider"].map(str::to_string);
+        let mut selector = Selector::new(SelectionMode::Playlist, &games(), &playlist);
+        assert_eq!(picks(&mut selector, 0, 7), [2, 0, 2, 2, 0, 2, 2]);
+    }
+
+    #[test]
+    fn zero_weight_games_come_up_only_when_nothing_else_is_left() {
+        let games = vec![("Pong".to_string(), 0.0), ("Circles".to_string(), 1.0)];
+        let mut selector = Selector::new(SelectionMode::Random, &games, &[]);
+        assert!(picks(&mut selector, 3, 100).iter().all(|&i| i == 1));
+
+        let games = vec![("Pong".to_string(), 0.0), ("Circles".to_string(), 0.0)];
+        let mut selector = Selector::new(SelectionMode::Random, &games, &[]);
+        assert!(picks(&mut selector, 3, 100).contains(&0));
+    }
+}
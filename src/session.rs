@@ -0,0 +1,543 @@
+use crate::canvas::Canvas;
+use crate::config::Config;
+use crate::difficulty::Ratings;
+use crate::game_handler::{GameHandler, GameOutcome};
+use crate::history::History;
+use crate::input::{BUTTONS, InputState, KEYS};
+use crate::loss_handler::LossHandler;
+use crate::overlay::Overlay;
+use crate::platform::{Platform, headless::Headless};
+use chrono::{DateTime, Local};
+use rand::{SeedableRng, rngs::StdRng};
+use raylib::prelude::*;
+use std::error::Error;
+use std::fs::File;
+use std::io::{self, BufReader, BufWriter, Read, Write};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+const MAGIC: &[u8; 4] = b"OVRP";
+const VERSION: u8 = 2;
+// record tags
+const FRAME: u8 = 0;
+const OUTCOME: u8 = 1;
+// outcome names in the order they are stored
+const OUTCOMES: [&str; 4] = ["won", "lost", "aborted", "timed out"];
+
+// everything the main loop reads from the outside world in one frame
+#[derive(Clone, Debug, PartialEq)]
+pub struct FrameInput {
+    pub delta_time: f32,
+    // milliseconds since the unix epoch
+    pub time: i64,
+    // desktop coordinates
+    pub cursor: Vector2,
+    // one bit per entry of KEYS and BUTTONS, set while held
+    pub keys: u8,
+    pub buttons: u8,
+    pub scroll: f32,
+    pub idle: Duration,
+}
+
+impl FrameInput {
+    pub fn capture(platform: &dyn Platform, delta_time: f32, scroll: f32) -> FrameInput {
+        let mut keys = 0;
+        for (bit, key) in KEYS.iter().enumerate() {
+            if platform.key_down(*key) {
+                keys |= 1 << bit;
+            }
+        }
+        let mut buttons = 0;
+        for (bit, button) in BUTTONS.iter().enumerate() {
+            if platform.button_down(*button) {
+                buttons |= 1 << bit;
+            }
+        }
+        FrameInput {
+            delta_time,
+            time: Local::now().timestamp_millis(),
+            cursor: platform.mouse_pos(),
+            keys,
+            buttons,
+            scroll,
+            idle: platform.idle_time(),
+        }
+    }
+
+    fn clock(&self) -> DateTime<Local> {
+        DateTime::from_timestamp_millis(self.time)
+            .unwrap_or_default()
+            .with_timezone(&Local)
+    }
+
+    // makes platform report the cursor, keys, buttons and idle time of this frame
+    pub fn apply(&self, platform: &mut Headless) {
+        platform.set_mouse_pos(self.cursor);
+        for (bit, key) in KEYS.iter().enumerate() {
+            platform.set_key(*key, self.keys & (1 << bit) != 0);
+        }
+        for (bit, button) in BUTTONS.iter().enumerate() {
+            platform.set_button(*button, self.buttons & (1 << bit) != 0);
+        }
+        platform.set_idle_time(self.idle);
+    }
+}
+
+// the main loop of the overlay minus the window, driven one FrameInput at a time
+// so a live run and a replay go through exactly the same steps
+pub struct Session {
+    game_handler: GameHandler,
+    loss_handler: LossHandler,
+    input: InputState,
+    // reports the current frame to InputState
+    mirror: Headless,
+    window_size: (i32, i32),
+}
+
+impl Session {
+    pub fn new(
+        config: &Config,
+        overlay: Overlay,
+        seed: u64,
+        ratings: Ratings,
+        now: DateTime<Local>,
+    ) -> Result<Session, Box<dyn Error>> {
+        let window_size = overlay.window_size();
+        let (x, y) = overlay.window_pos();
+        let window = rrect(x, y, window_size.0, window_size.1);
+        let mut rng = StdRng::seed_from_u64(seed);
+        // separate streams so the number of effects never changes which games come up
+        let mut game_handler = GameHandler::new(config, overlay, StdRng::from_rng(&mut rng), now)?;
+        game_handler.rate_difficulty(ratings);
+        let mut loss_handler = LossHandler::new(config, window, StdRng::from_rng(&mut rng));
+        game_handler.select_game();
+        loss_handler.select_effect();
+        Ok(Session {
+            game_handler,
+            loss_handler,
+            input: InputState::new(),
+            mirror: Headless::new(window_size),
+            window_size,
+        })
+    }
+
+    pub fn watch(&mut self, path: PathBuf) {
+        self.game_handler.watch(path);
+    }
+
+    pub fn keep_history(&mut self, history: History) {
+        self.game_handler.keep_history(history);
+    }
+
+    pub fn window_size(&self) -> (i32, i32) {
+        self.window_size
+    }
+
+    // runs and draws one frame, returns the outcome of a game that ended in it.
+    // input comes from frame, desktop is asked for screenshots and holds the cursor effects move
+    pub fn frame(&mut self, frame: &FrameInput, desktop: &dyn Platform, d: &mut dyn Canvas) -> Option<GameOutcome> {
+        frame.apply(&mut self.mirror);
+        self.input = self.input.next(&self.mirror, frame.scroll);
+        if let Some(config) = self.game_handler.reload_settings() {
+            self.loss_handler.reload(config);
+        }
+        self.game_handler.set_idle(frame.idle);
+        self.game_handler.set_clock(frame.clock());
+
+        if !self.loss_handler.finished(desktop) {
+            self.loss_handler.do_effect(d, frame.delta_time, desktop);
+            return None;
+        }
+        let outcome = self.game_handler.finished();
+        match &outcome {
+            Some(outcome) => {
+                if outcome.is_loss() {
+                    self.loss_handler.start(outcome, desktop);
+                    self.loss_handler.do_effect(d, frame.delta_time, desktop);
+                }
+            }
+            None => {
+                if self.game_handler.ready() {
+                    self.game_handler.do_frame(frame.delta_time, &self.input, d);
+                }
+            }
+        }
+        outcome
+    }
+
+    pub fn abort(&mut self) -> Option<GameOutcome> {
+        self.game_handler.abort()
+    }
+}
+
+// what a replay needs besides the frames
+#[derive(Clone, Debug)]
+pub struct Header {
+    pub seed: u64,
+    pub screen_size: (i32, i32),
+    pub monitors: Vec<Rectangle>,
+    // contents of settings.yaml when the recording started
+    pub settings: String,
+    // difficulty levels when the recording started, as json
+    pub difficulty: String,
+}
+
+// an outcome as stored in a recording, enough to tell if a replay went the same way
+#[derive(Clone, Debug, PartialEq)]
+pub struct RecordedOutcome {
+    pub name: &'static str,
+    pub score: i32,
+}
+
+impl RecordedOutcome {
+    fn from_outcome(outcome: &GameOutcome) -> RecordedOutcome {
+        RecordedOutcome {
+            name: outcome.name(),
+            score: outcome.stats().score,
+        }
+    }
+}
+
+fn read_array<const N: usize>(reader: &mut impl Read) -> io::Result<[u8; N]> {
+    let mut bytes = [0; N];
+    reader.read_exact(&mut bytes)?;
+    Ok(bytes)
+}
+
+fn read_f32(reader: &mut impl Read) -> io::Result<f32> {
+    Ok(f32::from_le_bytes(read_array(reader)?))
+}
+
+fn read_i32(reader: &mut impl Read) -> io::Result<i32> {
+    Ok(i32::from_le_bytes(read_array(reader)?))
+}
+
+fn read_u32(reader: &mut impl Read) -> io::Result<u32> {
+    Ok(u32::from_le_bytes(read_array(reader)?))
+}
+
+// a length and that many bytes of text, a broken length runs into the end of the file
+// instead of allocating whatever it says
+fn read_string(reader: &mut impl Read) -> Result<String, Box<dyn Error>> {
+    let length = read_u32(reader)? as u64;
+    let mut bytes = vec![];
+    reader.by_ref().take(length).read_to_end(&mut bytes)?;
+    if bytes.len() as u64 != length {
+        return Err(Box::new(io::Error::from(io::ErrorKind::UnexpectedEof)));
+    }
+    Ok(String::from_utf8(bytes)?)
+}
+
+// writes a session to a file as it is played, see Replay for reading it back
+pub struct Recording {
+    file: BufWriter<File>,
+}
+
+impl Recording {
+    pub fn create(path: &Path, header: &Header) -> io::Result<Recording> {
+        let mut file = BufWriter::new(File::create(path)?);
+        file.write_all(MAGIC)?;
+        file.write_all(&[VERSION])?;
+        file.write_all(&header.seed.to_le_bytes())?;
+        file.write_all(&header.screen_size.0.to_le_bytes())?;
+        file.write_all(&header.screen_size.1.to_le_bytes())?;
+        file.write_all(&(header.monitors.len() as u32).to_le_bytes())?;
+        for monitor in &header.monitors {
+            for value in [monitor.x, monitor.y, monitor.width, monitor.height] {
+                file.write_all(&value.to_le_bytes())?;
+            }
+        }
+        file.write_all(&(header.settings.len() as u32).to_le_bytes())?;
+        file.write_all(header.settings.as_bytes())?;
+        file.write_all(&(header.difficulty.len() as u32).to_le_bytes())?;
+        file.write_all(header.difficulty.as_bytes())?;
+        Ok(Recording { file })
+    }
+
+    pub fn frame(&mut self, frame: &FrameInput) -> io::Result<()> {
+        let idle = frame.idle.as_millis().min(u32::MAX as u128) as u32;
+        self.file.write_all(&[FRAME])?;
+        self.file.write_all(&frame.delta_time.to_le_bytes())?;
+        self.file.write_all(&frame.time.to_le_bytes())?;
+        self.file.write_all(&frame.cursor.x.to_le_bytes())?;
+        self.file.write_all(&frame.cursor.y.to_le_bytes())?;
+        self.file.write_all(&[frame.keys, frame.buttons])?;
+        self.file.write_all(&frame.scroll.to_le_bytes())?;
+        self.file.write_all(&idle.to_le_bytes())
+    }
+
+    pub fn outcome(&mut self, outcome: &GameOutcome) -> io::Result<()> {
+        let kind = OUTCOMES.iter().position(|name| *name == outcome.name()).unwrap_or(0);
+        self.file.write_all(&[OUTCOME, kind as u8])?;
+        self.file.write_all(&outcome.stats().score.to_le_bytes())
+    }
+
+    pub fn finish(mut self) -> io::Result<()> {
+        self.file.flush()
+    }
+}
+
+// a recorded session read back from a file
+pub struct Replay {
+    pub header: Header,
+    pub frames: Vec<FrameInput>,
+    pub outcomes: Vec<RecordedOutcome>,
+}
+
+impl Replay {
+    pub fn load(path: &Path) -> Result<Replay, Box<dyn Error>> {
+        let mut file = BufReader::new(File::open(path)?);
+        if &read_array::<4>(&mut file)? != MAGIC {
+            return Err(format!("{} is not a recording", path.display()).into());
+        }
+        let [version] = read_array(&mut file)?;
+        if version != VERSION {
+            return Err(format!("recording version {version} is not supported, expected {VERSION}").into());
+        }
+
+        let seed = u64::from_le_bytes(read_array(&mut file)?);
+        let screen_size = (read_i32(&mut file)?, read_i32(&mut file)?);
+        let mut monitors = vec![];
+        for _ in 0..read_u32(&mut file)? {
+            monitors.push(Rectangle::new(
+                read_f32(&mut file)?,
+                read_f32(&mut file)?,
+                read_f32(&mut file)?,
+                read_f32(&mut file)?,
+            ));
+        }
+        let header = Header {
+            seed,
+            screen_size,
+            monitors,
+            settings: read_string(&mut file)?,
+            difficulty: read_string(&mut file)?,
+        };
+
+        let mut frames = vec![];
+        let mut outcomes = vec![];
+        let mut tag = [0];
+        while file.read(&mut tag)? > 0 {
+            match tag[0] {
+                FRAME => frames.push(FrameInput {
+                    delta_time: read_f32(&mut file)?,
+                    time: i64::from_le_bytes(read_array(&mut file)?),
+                    cursor: Vector2::new(read_f32(&mut file)?, read_f32(&mut file)?),
+                    keys: read_array::<1>(&mut file)?[0],
+                    buttons: read_array::<1>(&mut file)?[0],
+                    scroll: read_f32(&mut file)?,
+                    idle: Duration::from_millis(read_u32(&mut file)? as u64),
+                }),
+                OUTCOME => {
+                    let [kind] = read_array(&mut file)?;
+                    let name = OUTCOMES
+                        .get(kind as usize)
+                        .copied()
+                        .ok_or(format!("unknown outcome {kind}"))?;
+                    outcomes.push(RecordedOutcome {
+                        name,
+                        score: read_i32(&mut file)?,
+                    });
+                }
+                other => return Err(format!("unknown record {other} after frame {}", frames.len()).into()),
+            }
+        }
+        Ok(Replay {
+            header,
+            frames,
+            outcomes,
+        })
+    }
+
+    // a session set up like the recorded one, on a desktop with the recorded monitors
+    pub fn session(&self) -> Result<Session, Box<dyn Error>> {
+        let (config, report) = Config::check(&self.header.settings);
+        if !report.is_ok() {
+            return Err(Box::new(report));
+        }
+        let mut desktop = Headless::new(self.header.screen_size);
+        desktop.set_monitors(self.header.monitors.clone());
+        let overlay = Overlay::new(&desktop, config.settings.monitor)?;
+        let start = self.frames.first().map(|frame| frame.clock()).unwrap_or_default();
+        // never saved, a replay must not change the real levels
+        let ratings = Ratings::from_json(&self.header.difficulty)?;
+        Session::new(&config, overlay, self.header.seed, ratings, start)
+    }
+
+    // compares the outcomes of a replay with the recorded ones
+    pub fn verify(&self, outcomes: &[GameOutcome]) -> Result<(), String> {
+        let replayed: Vec<RecordedOutcome> = outcomes.iter().map(RecordedOutcome::from_outcome).collect();
+        for (index, (expected, got)) in self.outcomes.iter().zip(&replayed).enumerate() {
+            if expected != got {
+                return Err(format!(
+                    "game {} was {} with score {} in the recording but {} with score {} in the replay",
+                    index + 1,
+                    expected.name,
+                    expected.score,
+                    got.name,
+                    got.score
+                ));
+            }
+        }
+        if self.outcomes.len() != replayed.len() {
+            return Err(format!(
+                "{} games in the recording but {} in the replay",
+                self.outcomes.len(),
+                replayed.len()
+            ));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::canvas::Recorder;
+    use crate::input::BUTTONS;
+    use crate::platform::Button;
+
+    const SCREEN: (i32, i32) = (320, 200);
+    const SETTINGS: &str = "Settings:
+  cooldown: 0.5
+  selection: playlist
+  playlist: [Circles, Avoider]
+Pong:
+  enabled: false
+Circles:
+  time_limit: 2.0
+Avoider:
+  time_limit: 3.0
+Effects:
+  Flash:
+    duration: 1.0
+  Shake:
+    duration: 1.0
+    fade_out: 1.0
+Activity:
+  start_idle_limit: 0
+  pause_idle_limit: 0
+";
+
+    fn temp_path(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("session-test-{}-{name}.ovrp", std::process::id()))
+    }
+
+    // a cursor going round in circles with a click every quarter second
+    fn scripted_frames(count: usize) -> Vec<FrameInput> {
+        let start = 1_740_819_600_000;
+        let click = BUTTONS.iter().position(|b| *b == Button::Left).unwrap();
+        (0..count)
+            .map(|i| {
+                let angle = i as f32 * 0.05;
+                FrameInput {
+                    delta_time: 1.0 / 60.0,
+                    time: start + (i as i64 * 1000) / 60,
+                    cursor: Vector2::new(160.0 + 120.0 * angle.cos(), 100.0 + 80.0 * angle.sin()),
+                    keys: 0,
+                    buttons: if i % 15 < 2 { 1 << click } else { 0 },
+                    scroll: 0.0,
+                    idle: Duration::ZERO,
+                }
+            })
+            .collect()
+    }
+
+    fn play(session: &mut Session, frames: &[FrameInput], mut recording: Option<&mut Recording>) -> Vec<GameOutcome> {
+        let mut desktop = Headless::new(SCREEN);
+        let mut canvas = Recorder::new();
+        let mut outcomes = vec![];
+        for frame in frames {
+            frame.apply(&mut desktop);
+            let outcome = session.frame(frame, &desktop, &mut canvas);
+            canvas.take();
+            if let Some(recording) = recording.as_deref_mut() {
+                recording.frame(frame).unwrap();
+                if let Some(outcome) = &outcome {
+                    recording.outcome(outcome).unwrap();
+                }
+            }
+            outcomes.extend(outcome);
+        }
+        outcomes
+    }
+
+    fn record(path: &Path, frames: &[FrameInput]) -> Vec<GameOutcome> {
+        let header = Header {
+            seed: 11,
+            screen_size: SCREEN,
+            monitors: vec![rrect(0, 0, SCREEN.0, SCREEN.1)],
+            settings: SETTINGS.to_string(),
+            difficulty: r#"{"Avoider":0.5}"#.to_string(),
+        };
+        let mut desktop = Headless::new(header.screen_size);
+        desktop.set_monitors(header.monitors.clone());
+        let (config, report) = Config::check(SETTINGS);
+        assert!(report.is_ok(), "{report}");
+        let overlay = Overlay::new(&desktop, config.settings.monitor).unwrap();
+        let ratings = Ratings::from_json(&header.difficulty).unwrap();
+        let mut session = Session::new(&config, overlay, header.seed, ratings, frames[0].clock()).unwrap();
+
+        let mut recording = Recording::create(path, &header).unwrap();
+        let outcomes = play(&mut session, frames, Some(&mut recording));
+        recording.finish().unwrap();
+        outcomes
+    }
+
+    #[test]
+    fn recorded_session_replays_the_same() {
+        let path = temp_path("round-trip");
+        let frames = scripted_frames(20 * 60);
+        let recorded = record(&path, &frames);
+        let replay = Replay::load(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert!(recorded.len() >= 3, "{recorded:?}");
+        assert_eq!(replay.header.seed, 11);
+        assert_eq!(replay.header.settings, SETTINGS);
+        assert_eq!(replay.frames, frames);
+        assert_eq!(replay.outcomes.len(), recorded.len());
+
+        let replayed = play(&mut replay.session().unwrap(), &replay.frames, None);
+        assert_eq!(replay.verify(&replayed), Ok(()));
+        // one game less is caught too
+        assert!(replay.verify(&replayed[1..]).is_err());
+    }
+
+    #[test]
+    fn broken_files_are_errors() {
+        let path = temp_path("broken");
+        record(&path, &scripted_frames(60));
+        let bytes = std::fs::read(&path).unwrap();
+
+        let load = |bytes: &[u8]| {
+            std::fs::write(&path, bytes).unwrap();
+            Replay::load(&path).map(|replay| replay.frames.len()).map_err(|e| e.to_string())
+        };
+        assert_eq!(load(&bytes), Ok(60));
+        // cut off in the middle of the last frame
+        assert!(load(&bytes[..bytes.len() - 3]).is_err());
+        // cut off in the header
+        assert!(load(&bytes[..20]).is_err());
+        assert!(load(b"").is_err());
+
+        let mut bad_magic = bytes.clone();
+        bad_magic[0] = b'X';
+        assert!(load(&bad_magic).unwrap_err().ends_with("is not a recording"));
+
+        let mut bad_version = bytes.clone();
+        bad_version[4] = VERSION + 1;
+        assert!(load(&bad_version).unwrap_err().contains("not supported"));
+
+        // a settings length far past the end of the file
+        let mut bad_length = bytes.clone();
+        let at = 4 + 1 + 8 + 8 + 4 + 16;
+        bad_length[at..at + 4].copy_from_slice(&u32::MAX.to_le_bytes());
+        assert!(load(&bad_length).is_err());
+
+        let mut bad_tag = bytes.clone();
+        bad_tag.push(7);
+        assert!(load(&bad_tag).unwrap_err().starts_with("unknown record 7"));
+        std::fs::remove_file(&path).unwrap();
+    }
+}
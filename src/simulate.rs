@@ -0,0 +1,430 @@
+use crate::canvas::{DrawCommand, Recorder};
+use crate::game_handler::{Game, GameContext, GameOutcome};
+use crate::input::InputState;
+use crate::platform::{Button, Key, headless::Headless};
+use rand::{SeedableRng, rngs::StdRng};
+use raylib::prelude::*;
+
+// one change to the simulated input, applied before the logic of its frame
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum InputEvent {
+    // cursor position in window coordinates
+    MoveMouse(Vector2),
+    Key(Key, bool),
+    Button(Button, bool),
+    // wheel movement, only for the frame it is on
+    Scroll(f32),
+}
+
+// scripted input, every event is keyed by the frame it happens on
+#[derive(Clone, Debug, Default)]
+pub struct Timeline {
+    events: Vec<(usize, InputEvent)>,
+}
+
+impl Timeline {
+    pub fn new() -> Timeline {
+        Timeline { events: vec![] }
+    }
+
+    pub fn at(mut self, frame: usize, event: InputEvent) -> Timeline {
+        self.events.push((frame, event));
+        self
+    }
+
+    // sets up input for the frame and returns its scroll
+    fn apply(&self, frame: usize, input: &mut Headless) -> f32 {
+        let mut scroll = 0.0;
+        for (_, event) in self.events.iter().filter(|(at, _)| *at == frame) {
+            match *event {
+                InputEvent::MoveMouse(pos) => input.set_mouse_pos(pos),
+                InputEvent::Key(key, down) => input.set_key(key, down),
+                InputEvent::Button(button, down) => input.set_button(button, down),
+                InputEvent::Scroll(amount) => scroll += amount,
+            }
+        }
+        scroll
+    }
+}
+
+// what happened during one step
+#[derive(Clone, Debug)]
+pub struct Frame {
+    pub index: usize,
+    // seconds since the round started, after this step
+    pub time: f32,
+    pub mouse_pos: Vector2,
+    pub commands: Vec<DrawCommand>,
+}
+
+#[derive(Clone, Debug)]
+pub struct Simulation {
+    // None if the game was still going after the last step
+    pub outcome: Option<GameOutcome>,
+    pub trace: Vec<Frame>,
+}
+
+// plays a round of game at difficulty level in area for up to steps fixed steps, without a window or global input
+// the same seed and timeline always give the same simulation
+pub fn simulate(
+    game: &mut dyn Game,
+    area: Rectangle,
+    seed: u64,
+    level: f32,
+    steps: usize,
+    delta_time: f32,
+    timeline: &Timeline,
+) -> Simulation {
+    simulate_with(game, area, seed, level, steps, delta_time, |frame, _, input| {
+        timeline.apply(frame, input)
+    })
+}
+
+// like simulate, but bot sets the input every step after seeing what was drawn the step before
+// and returns the scroll for the step
+pub fn simulate_with(
+    game: &mut dyn Game,
+    area: Rectangle,
+    seed: u64,
+    level: f32,
+    steps: usize,
+    delta_time: f32,
+    mut bot: impl FnMut(usize, &[DrawCommand], &mut Headless) -> f32,
+) -> Simulation {
+    let mut platform = Headless::new(((area.x + area.width) as i32, (area.y + area.height) as i32));
+    platform.set_mouse_pos(Vector2::new(
+        area.x + area.width / 2.0,
+        area.y + area.height / 2.0,
+    ));
+    let mut input = InputState::new().next(&platform, 0.0);
+    let mut rng = StdRng::seed_from_u64(seed);
+    game.set_difficulty(level);
+    game.on_start(&mut GameContext {
+        area,
+        rng: &mut rng,
+    });
+
+    let mut trace: Vec<Frame> = vec![];
+    let mut outcome = None;
+    for index in 0..steps {
+        let last = trace.last().map(|frame| frame.commands.as_slice()).unwrap_or(&[]);
+        let scroll = bot(index, last, &mut platform);
+
+        input = input.next(&platform, scroll);
+        game.logic(&input, delta_time);
+        let mut recorder = Recorder::new();
+        game.draw(&mut recorder, 1.0);
+        trace.push(Frame {
+            index,
+            time: (index + 1) as f32 * delta_time,
+            mouse_pos: input.cursor,
+            commands: recorder.take(),
+        });
+
+        if let Some(result) = game.is_finished() {
+            game.on_end(&result);
+            outcome = Some(result);
+            break;
+        }
+    }
+    Simulation {
+        outcome,
+        trace,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Config;
+    use crate::games::{avoider::Avoider, circles::Circles, pong::Pong};
+    use crate::platform::Platform;
+
+    const STEP: f32 = 1.0 / 120.0;
+
+    fn area() -> Rectangle {
+        Rectangle::new(0.0, 0.0, 1000.0, 500.0)
+    }
+
+    // centers of the circles drawn in color
+    fn circles(commands: &[DrawCommand], color: Color) -> Vec<Vector2> {
+        let color = [color.r, color.g, color.b, color.a];
+        commands
+            .iter()
+            .filter_map(|command| match command {
+                DrawCommand::Circle { x, y, color: c, .. } if *c == color => {
+                    Some(Vector2::new(*x as f32, *y as f32))
+                }
+                _ => None,
+            })
+            .collect()
+    }
+
+    // middle of the left paddle, the field is drawn first and the left paddle right after the ball
+    fn left_paddle(commands: &[DrawCommand]) -> Option<f32> {
+        commands.iter().find_map(|command| match command {
+            DrawCommand::Rectangle { y, height, color, .. } if *color == [0, 0, 0, 255] => {
+                Some((*y + *height / 2) as f32)
+            }
+            _ => None,
+        })
+    }
+
+    // steps out of the way of enemies heading for the player and otherwise drifts back to the
+    // middle, enemies are told apart from last frame's positions to know where they are going
+    struct Dodger {
+        enemies: Vec<Vector2>,
+    }
+
+    impl Dodger {
+        fn new() -> Dodger {
+            Dodger { enemies: vec![] }
+        }
+
+        fn step(&mut self, commands: &[DrawCommand], input: &mut Headless) -> f32 {
+            let enemies = circles(commands, Color::RED);
+            let Some(player) = circles(commands, Color::GREENYELLOW).first().copied() else {
+                return 0.0;
+            };
+            let center = Vector2::new(area().width / 2.0, area().height / 2.0);
+            let mut target = player + (center - player) * 0.05;
+            for enemy in &enemies {
+                // an enemy moves about 4 pixels a step, the closest old position is where it was
+                let before = self.enemies.iter().copied().min_by(|a, b| {
+                    a.distance_to(*enemy).total_cmp(&b.distance_to(*enemy))
+                });
+                let Some(before) = before.filter(|b| (0.5..20.0).contains(&b.distance_to(*enemy))) else {
+                    continue;
+                };
+                let heading = (*enemy - before).normalized();
+                let offset = player - *enemy;
+                let ahead = offset.dot(heading);
+                let side = offset - heading * ahead;
+                if ahead > -60.0 && side.length() < 80.0 {
+                    let away = if side.length() > 1.0 {
+                        side.normalized()
+                    } else {
+                        Vector2::new(-heading.y, heading.x)
+                    };
+                    target += away * 150.0;
+                }
+            }
+            self.enemies = enemies;
+            input.set_mouse_pos(Vector2::new(
+                target.x.clamp(60.0, area().width - 60.0),
+                target.y.clamp(60.0, area().height - 60.0),
+            ));
+            0.0
+        }
+    }
+
+    fn avoider(spawn_timer: f32) -> Avoider {
+        let mut config = Config::default().avoider;
+        config.spawn_timer = spawn_timer;
+        Avoider::new(&config)
+    }
+
+    fn survived(simulation: &Simulation) -> f32 {
+        match &simulation.outcome {
+            Some(outcome) => outcome.stats().duration.as_secs_f32(),
+            None => panic!("the round should end within the simulated steps"),
+        }
+    }
+
+    // moves the left paddle towards the ball with the arrow keys
+    fn follow_ball(commands: &[DrawCommand], input: &mut Headless) -> f32 {
+        let ball = circles(commands, Color::BLACK).first().copied();
+        let (up, down) = match (ball, left_paddle(commands)) {
+            (Some(ball), Some(paddle)) if ball.y < paddle - 10.0 => (true, false),
+            (Some(ball), Some(paddle)) if ball.y > paddle + 10.0 => (false, true),
+            _ => (false, false),
+        };
+        input.set_key(Key::Up, up);
+        input.set_key(Key::Down, down);
+        0.0
+    }
+
+
+    #[test]
+    fn trace_follows_the_timeline() {
+        let config = Config::default().circles;
+        // circles stay in the inner part of the area, a click in the corner misses them all
+        let corner = Vector2::new(1.0, 1.0);
+        let timeline = Timeline::new()
+            .at(2, InputEvent::MoveMouse(corner))
+            .at(2, InputEvent::Button(Button::Left, true))
+            .at(3, InputEvent::Button(Button::Left, false));
+        let round = simulate(&mut Circles::new(&config), area(), 1, 0.0, 5, STEP, &timeline);
+        assert_eq!(round.outcome, None);
+        let indexes: Vec<usize> = round.trace.iter().map(|frame| frame.index).collect();
+        assert_eq!(indexes, vec![0, 1, 2, 3, 4]);
+        assert_eq!(round.trace[1].mouse_pos, Vector2::new(500.0, 250.0));
+        assert_eq!(round.trace[4].mouse_pos, corner);
+        assert_eq!(round.trace[4].time, 5.0 * STEP);
+        assert_eq!(circles(&round.trace[4].commands, Color::RED).len(), config.min_amount as usize);
+    }
+    #[test]
+    fn avoider_standing_still_is_hit() {
+        // an enemy every 0.6 seconds aimed at the player
+        let mut config = Config::default().avoider;
+        config.spawn_timer = 0.6;
+        let steps = ((config.time_limit + 1.0) / STEP) as usize;
+        let round = simulate_with(&mut Avoider::new(&config), area(), 1, 0.0, steps, STEP, |_, _, _| 0.0);
+        assert!(matches!(round.outcome, Some(GameOutcome::Lost(_))), "got {:?}", round.outcome);
+    }
+
+    #[test]
+    fn circles_clicked_by_a_bot_win() {
+        let config = Config::default().circles;
+        // moves onto a circle seen in the last frame and clicks it, letting go in between
+        let round = simulate_with(&mut Circles::new(&config), area(), 1, 0.0, 120, STEP, |frame, commands, input| {
+            match circles(commands, Color::RED).first() {
+                Some(target) if frame % 2 == 0 => {
+                    input.set_mouse_pos(*target);
+                    input.set_button(Button::Left, true);
+                }
+                _ => input.set_button(Button::Left, false),
+            }
+            0.0
+        });
+        assert!(matches!(round.outcome, Some(GameOutcome::Won(_))), "got {:?}", round.outcome);
+    }
+
+    #[test]
+    fn circles_time_out_without_clicks() {
+        let config = Config::default().circles;
+        let steps = (config.time_limit / STEP) as usize + 10;
+        let round = simulate(&mut Circles::new(&config), area(), 1, 0.0, steps, STEP, &Timeline::new());
+        let Some(GameOutcome::TimedOut(stats)) = &round.outcome else {
+            panic!("a round without clicks should time out, got {:?}", round.outcome);
+        };
+        assert_eq!(stats.score, 0);
+        assert!((round.trace.last().unwrap().time - config.time_limit).abs() <= STEP * 2.0);
+    }
+
+    #[test]
+    fn pong_paddle_moves_with_timeline_keys() {
+        let config = Config::default().pong;
+        let timeline = Timeline::new()
+            .at(0, InputEvent::Key(Key::Up, true))
+            .at(30, InputEvent::Key(Key::Up, false))
+            .at(30, InputEvent::Key(Key::Down, true))
+            .at(60, InputEvent::Key(Key::Down, false));
+        let round = simulate(&mut Pong::new(&config), area(), 1, 0.0, 90, STEP, &timeline);
+        assert_eq!(round.outcome, None);
+        let paddle = |frame: usize| left_paddle(&round.trace[frame].commands).unwrap();
+        // as far down as it went up, then it stays put
+        assert!(paddle(29) < paddle(0));
+        assert!((paddle(59) - paddle(0)).abs() <= 1.0);
+        assert_eq!(paddle(89), paddle(60));
+    }
+
+    #[test]
+    fn timeline_scroll_lasts_one_frame() {
+        let timeline = Timeline::new()
+            .at(1, InputEvent::Scroll(1.0))
+            .at(1, InputEvent::Scroll(0.5))
+            .at(2, InputEvent::MoveMouse(Vector2::new(10.0, 20.0)));
+        let mut input = Headless::new((100, 100));
+        let scrolls: Vec<f32> = (0..3).map(|frame| timeline.apply(frame, &mut input)).collect();
+        assert_eq!(scrolls, vec![0.0, 1.5, 0.0]);
+        assert_eq!(input.mouse_pos(), Vector2::new(10.0, 20.0));
+    }
+
+    #[test]
+    fn avoider_bot_survives_spawn_timer_0_6() {
+        let time_limit = Config::default().avoider.time_limit;
+        let steps = ((time_limit + 1.0) / STEP) as usize;
+        let (mut wins, mut total) = (0, 0.0);
+        for seed in 0..10 {
+            // an enemy every 0.6 seconds aimed at the player, standing still is hit almost at once
+            let still = simulate_with(&mut avoider(0.6), area(), seed, 0.0, steps, STEP, |_, _, _| 0.0);
+            assert!(matches!(still.outcome, Some(GameOutcome::Lost(_))));
+            assert!(survived(&still) < 2.0);
+
+            let mut dodger = Dodger::new();
+            let bot = simulate_with(&mut avoider(0.6), area(), seed, 0.0, steps, STEP, |_, commands, input| {
+                dodger.step(commands, input)
+            });
+            assert!(survived(&bot) > survived(&still), "seed {seed}");
+            if let Some(GameOutcome::Won(stats)) = &bot.outcome {
+                assert_eq!(stats.duration.as_secs_f32(), time_limit);
+                wins += 1;
+            }
+            total += survived(&bot);
+        }
+        // the bot is not perfect, but it lasts most rounds
+        assert!(wins >= 5, "won {wins} of 10");
+        assert!(total / 10.0 > 10.0, "survived {}s on average", total / 10.0);
+    }
+
+    #[test]
+    fn avoider_is_deterministic() {
+        let run = || {
+            let mut dodger = Dodger::new();
+            simulate_with(&mut avoider(0.6), area(), 5, 0.0, 20 * 120, STEP, |_, commands, input| {
+                dodger.step(commands, input)
+            })
+        };
+        let (first, second) = (run(), run());
+        assert_eq!(first.outcome, second.outcome);
+        assert_eq!(first.trace.len(), second.trace.len());
+        for (a, b) in first.trace.iter().zip(&second.trace) {
+            assert_eq!((a.index, a.time, a.mouse_pos), (b.index, b.time, b.mouse_pos));
+            assert_eq!(a.commands, b.commands);
+        }
+    }
+
+    #[test]
+    fn level_is_set_before_the_round_starts() {
+        let config = Config::default().circles;
+        let hardest = simulate(&mut Circles::new(&config), area(), 9, 1.0, 1, STEP, &Timeline::new());
+        assert_eq!(circles(&hardest.trace[0].commands, Color::RED).len(), config.max_amount as usize);
+    }
+
+    #[test]
+    fn circles_clicked_from_a_timeline_win() {
+        let config = Config::default().circles;
+        // one step without input to see where the circles are
+        let first = simulate(&mut Circles::new(&config), area(), 9, 0.0, 1, STEP, &Timeline::new());
+        assert_eq!(first.outcome, None);
+        let targets = circles(&first.trace[0].commands, Color::RED);
+        assert_eq!(targets.len(), config.min_amount as usize);
+
+        // the same seed puts them in the same places, click each one on its own frame
+        let mut timeline = Timeline::new();
+        for (i, target) in targets.iter().enumerate() {
+            let frame = i * 2;
+            timeline = timeline
+                .at(frame, InputEvent::MoveMouse(*target))
+                .at(frame, InputEvent::Button(Button::Left, true))
+                .at(frame + 1, InputEvent::Button(Button::Left, false));
+        }
+        let round = simulate(&mut Circles::new(&config), area(), 9, 0.0, 120, STEP, &timeline);
+        let Some(GameOutcome::Won(stats)) = &round.outcome else {
+            panic!("clicking every circle should win, got {:?}", round.outcome);
+        };
+        assert_eq!(stats.score, config.min_amount);
+        assert_eq!(round.trace.len(), targets.len() * 2 - 1);
+        assert_eq!(round.trace.last().map(|frame| frame.mouse_pos), targets.last().copied());
+    }
+
+    #[test]
+    fn pong_bot_returns_the_ball() {
+        let config = Config::default().pong;
+        let steps = 60 * 120;
+        let idle = simulate_with(&mut Pong::new(&config), area(), 2, 0.0, steps, STEP, |_, _, _| 0.0);
+        let bot = simulate_with(&mut Pong::new(&config), area(), 2, 0.0, steps, STEP, |_, commands, input| {
+            follow_ball(commands, input)
+        });
+        let Some(GameOutcome::Lost(idle_stats)) = &idle.outcome else {
+            panic!("a paddle that never moves should lose, got {:?}", idle.outcome);
+        };
+        assert_eq!(idle_stats.score, 0);
+        let Some(outcome) = &bot.outcome else {
+            panic!("the round should end within {steps} steps");
+        };
+        assert!(outcome.stats().score > 0);
+        assert!(outcome.stats().duration > idle_stats.duration);
+    }
+}
@@ -3,29 +3,54 @@ use std::time::Duration;
 pub struct Timer {
     duration: Duration,
     elapsed: Duration,
+    paused: bool,
 }
 
 impl Timer {
     pub fn new(duration: Duration) -> Timer {
         Timer {
-            duration: duration,
+            duration,
             elapsed: Duration::from_secs(0),
+            paused: false,
         }
     }
 
     pub fn update(&mut self, delta_time: f32) {
-        self.elapsed += Duration::from_secs_f32(delta_time);
+        if !self.paused {
+            self.elapsed += Duration::from_secs_f32(delta_time);
+        }
+    }
+
+    pub fn pause(&mut self) {
+        self.paused = true;
+    }
+
+    pub fn resume(&mut self) {
+        self.paused = false;
     }
 
     pub fn time_left(&mut self) -> i32 {
         self.duration.abs_diff(self.elapsed).as_secs_f32().round() as i32
     }
 
+    pub fn elapsed(&self) -> Duration {
+        self.elapsed
+    }
+
+    pub fn duration(&self) -> Duration {
+        self.duration
+    }
+
+    pub fn set_duration(&mut self, duration: Duration) {
+        self.duration = duration;
+    }
+
     pub fn is_finished(&self) -> bool {
         self.elapsed >= self.duration
     }
 
     pub fn reset(&mut self) {
         self.elapsed = Duration::from_secs(0);
+        self.paused = false;
     }
 }
\ No newline at end of file